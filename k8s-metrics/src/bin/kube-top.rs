@@ -0,0 +1,421 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use k8s_openapi as k8s;
+use kube::api;
+
+use k8s::api::core::v1 as corev1;
+use k8s::serde_json as json;
+use k8s_metrics::top::{NodeResources, SortBy, TopNodes, TopPods};
+use k8s_metrics::MetricsClient;
+
+/// Display resource (CPU/memory) usage of pods and nodes, like `kubectl top`
+///
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Display resource usage of pods
+    ///
+    #[command(alias = "pods", alias = "po")]
+    Pod(PodArgs),
+    /// Display resource usage of nodes
+    ///
+    #[command(alias = "nodes", alias = "no")]
+    Node(NodeArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct PodArgs {
+    /// Only show metrics of this pod
+    ///
+    name: Option<String>,
+
+    /// Namespace, defaults to the namespace of current context
+    ///
+    #[arg(short, long)]
+    namespace: Option<String>,
+
+    /// List pods in all namespaces
+    ///
+    #[arg(short = 'A', long, conflicts_with = "namespace")]
+    all_namespaces: bool,
+
+    /// Label selector to filter pods on
+    ///
+    #[arg(short = 'l', long)]
+    selector: Option<String>,
+
+    /// Print usage of individual containers
+    ///
+    #[arg(long)]
+    containers: bool,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct NodeArgs {
+    /// Only show metrics of this node
+    ///
+    name: Option<String>,
+
+    /// Label selector to filter nodes on
+    ///
+    #[arg(short = 'l', long)]
+    selector: Option<String>,
+
+    /// Percentages relative to node capacity instead of allocatable resources
+    ///
+    #[arg(long)]
+    show_capacity: bool,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct CommonArgs {
+    /// Sort by cpu or memory usage
+    ///
+    #[arg(long)]
+    sort_by: Option<SortBy>,
+
+    /// Do not print headers
+    ///
+    #[arg(long)]
+    no_headers: bool,
+
+    /// Output format
+    ///
+    #[arg(short, long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum Output {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+}
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    let client = kube::Client::try_default().await?;
+    let output = match cli.command {
+        Command::Pod(args) => top_pods(client, &args).await?,
+        Command::Node(args) => top_nodes(client, &args).await?,
+    };
+    print!("{output}");
+    Ok(())
+}
+
+async fn top_pods(client: kube::Client, args: &PodArgs) -> Result<String, Error> {
+    let namespace = args
+        .namespace
+        .clone()
+        .unwrap_or_else(|| client.default_namespace().to_string());
+    let metrics = MetricsClient::new(client);
+    let selector = args.selector.as_deref();
+
+    let pods = match &args.name {
+        Some(name) => vec![metrics.pod(&namespace, name).await?],
+        None if args.all_namespaces => metrics.all_pods_with_selector(selector).await?,
+        None => metrics.pods(&namespace, selector).await?,
+    };
+
+    let common = &args.common;
+    match common.output {
+        Output::Table => {
+            let table = TopPods::new()
+                .containers(args.containers)
+                .namespace(args.all_namespaces)
+                .no_headers(common.no_headers)
+                .sort_by(common.sort_by.unwrap_or_default())
+                .render(&pods)?;
+            Ok(table)
+        }
+        output => serialize(pods, output),
+    }
+}
+
+async fn top_nodes(client: kube::Client, args: &NodeArgs) -> Result<String, Error> {
+    let nodes_api = api::Api::<corev1::Node>::all(client.clone());
+    let metrics = MetricsClient::new(client);
+    let selector = args.selector.as_deref();
+
+    let metrics = match &args.name {
+        Some(name) => vec![metrics.node(name).await?],
+        None => metrics.nodes(selector).await?,
+    };
+
+    let common = &args.common;
+    match common.output {
+        Output::Table => {
+            // Node resources are only shown in tables, so nodes are not listed otherwise
+            let nodes = match &args.name {
+                Some(name) => vec![nodes_api.get(name).await?],
+                None => {
+                    let lp = api::ListParams::default();
+                    let lp = match selector {
+                        Some(selector) => lp.labels(selector),
+                        None => lp,
+                    };
+                    nodes_api.list(&lp).await?.items
+                }
+            };
+            let resources = if args.show_capacity {
+                NodeResources::Capacity
+            } else {
+                NodeResources::Allocatable
+            };
+            let table = TopNodes::new()
+                .no_headers(common.no_headers)
+                .sort_by(common.sort_by.unwrap_or_default())
+                .resources(resources)
+                .render(&metrics, &nodes)?;
+            Ok(table)
+        }
+        output => serialize(metrics, output),
+    }
+}
+
+fn serialize<K>(items: Vec<K>, output: Output) -> Result<String, Error>
+where
+    K: k8s::ListableResource + serde::Serialize,
+{
+    let list = k8s::List {
+        items,
+        metadata: Default::default(),
+    };
+    let text = match output {
+        Output::Json => json::to_string_pretty(&list)? + "\n",
+        Output::Yaml => serde_yaml::to_string(&list)?,
+        Output::Table => unreachable!("tables are rendered separately"),
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kube::client::Body;
+    use tower_test::mock;
+
+    const POD_METRICS_LIST: &str = r#"{
+  "kind": "PodMetricsList",
+  "apiVersion": "metrics.k8s.io/v1beta1",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "web-0", "namespace": "default"},
+      "timestamp": "2022-10-09T11:51:20Z",
+      "window": "15s",
+      "containers": [
+        {"name": "nginx", "usage": {"cpu": "250m", "memory": "64Mi"}},
+        {"name": "envoy", "usage": {"cpu": "1500u", "memory": "128Mi"}}
+      ]
+    },
+    {
+      "metadata": {"name": "web-1", "namespace": "default"},
+      "timestamp": "2022-10-09T11:51:20Z",
+      "window": "15s",
+      "containers": [
+        {"name": "nginx", "usage": {"cpu": "1", "memory": "32Mi"}}
+      ]
+    }
+  ]
+}"#;
+
+    const NODE_METRICS_LIST: &str = r#"{
+  "kind": "NodeMetricsList",
+  "apiVersion": "metrics.k8s.io/v1beta1",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "docker-desktop"},
+      "timestamp": "2022-10-09T11:41:45Z",
+      "window": "23.5s",
+      "usage": {"cpu": "196382978n", "memory": "1848836Ki"}
+    }
+  ]
+}"#;
+
+    const NODE_LIST: &str = r#"{
+  "kind": "NodeList",
+  "apiVersion": "v1",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "docker-desktop"},
+      "status": {
+        "allocatable": {"cpu": "4", "memory": "8Gi"},
+        "capacity": {"cpu": "8", "memory": "16Gi"}
+      }
+    }
+  ]
+}"#;
+
+    type Handle = mock::Handle<http::Request<Body>, http::Response<Body>>;
+
+    fn mock_client() -> (kube::Client, Handle) {
+        let (service, handle) = mock::pair::<http::Request<Body>, http::Response<Body>>();
+        (kube::Client::new(service, "default"), handle)
+    }
+
+    async fn respond(handle: &mut Handle, path_and_query: &str, body: &str) {
+        let (request, send) = handle.next_request().await.expect("request expected");
+        assert_eq!(request.uri().path_and_query().unwrap(), path_and_query);
+        let response = http::Response::builder()
+            .body(Body::from(body.as_bytes().to_vec()))
+            .unwrap();
+        send.send_response(response);
+    }
+
+    fn pod_args(args: &[&str]) -> PodArgs {
+        let cli = Cli::parse_from(["kube-top", "pod"].iter().chain(args));
+        match cli.command {
+            Command::Pod(args) => args,
+            Command::Node(_) => unreachable!(),
+        }
+    }
+
+    fn node_args(args: &[&str]) -> NodeArgs {
+        let cli = Cli::parse_from(["kube-top", "node"].iter().chain(args));
+        match cli.command {
+            Command::Node(args) => args,
+            Command::Pod(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn pods_table() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(
+                &mut handle,
+                "/apis/metrics.k8s.io/v1beta1/namespaces/default/pods?&labelSelector=app%3Dweb",
+                POD_METRICS_LIST,
+            )
+            .await;
+        });
+
+        let args = pod_args(&["-l", "app=web", "--sort-by", "cpu", "--no-headers"]);
+        let output = top_pods(client, &args).await.unwrap();
+        server.await.unwrap();
+
+        let lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+        assert_eq!(lines, ["web-1   1000m   32Mi", "web-0   252m    192Mi"]);
+    }
+
+    #[tokio::test]
+    async fn pods_containers_all_namespaces() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(
+                &mut handle,
+                "/apis/metrics.k8s.io/v1beta1/pods?",
+                POD_METRICS_LIST,
+            )
+            .await;
+        });
+
+        let args = pod_args(&["-A", "--containers"]);
+        let output = top_pods(client, &args).await.unwrap();
+        server.await.unwrap();
+
+        let lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "NAMESPACE   POD     NAME    CPU(cores)   MEMORY(bytes)",
+                "default     web-0   envoy   2m           128Mi",
+                "default     web-0   nginx   250m         64Mi",
+                "default     web-1   nginx   1000m        32Mi",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pods_json() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(
+                &mut handle,
+                "/apis/metrics.k8s.io/v1beta1/namespaces/kube-system/pods?",
+                POD_METRICS_LIST,
+            )
+            .await;
+        });
+
+        let args = pod_args(&["-n", "kube-system", "-o", "json"]);
+        let output = top_pods(client, &args).await.unwrap();
+        server.await.unwrap();
+
+        let list = json::from_str::<k8s::List<k8s_metrics::v1beta1::PodMetrics>>(&output).unwrap();
+        assert_eq!(list.items.len(), 2);
+        assert!(output.contains(r#""kind": "PodMetricsList""#));
+    }
+
+    #[tokio::test]
+    async fn nodes_table() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(
+                &mut handle,
+                "/apis/metrics.k8s.io/v1beta1/nodes?",
+                NODE_METRICS_LIST,
+            )
+            .await;
+            respond(&mut handle, "/api/v1/nodes?", NODE_LIST).await;
+        });
+
+        let args = node_args(&["--show-capacity"]);
+        let output = top_nodes(client, &args).await.unwrap();
+        server.await.unwrap();
+
+        let lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "NAME             CPU(cores)   CPU(%)   MEMORY(bytes)   MEMORY(%)",
+                "docker-desktop   197m         2%       1805Mi          11%",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn nodes_yaml() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(
+                &mut handle,
+                "/apis/metrics.k8s.io/v1beta1/nodes?",
+                NODE_METRICS_LIST,
+            )
+            .await;
+        });
+
+        // Nodes are not listed, the mock would fail the request once the server is done
+        let args = node_args(&["-o", "yaml"]);
+        let output = top_nodes(client, &args).await.unwrap();
+        server.await.unwrap();
+
+        assert!(output.contains("kind: NodeMetricsList"));
+        assert!(output.contains("name: docker-desktop"));
+    }
+
+    #[test]
+    fn invalid_sort_by() {
+        Cli::try_parse_from(["kube-top", "pod", "--sort-by", "disk"]).unwrap_err();
+    }
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+
+use kube::api;
+
+use super::*;
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+/// Typed client for `metrics.k8s.io` API
+///
+#[derive(Clone)]
+pub struct MetricsClient {
+    client: kube::Client,
+}
+
+impl fmt::Debug for MetricsClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsClient")
+            .field("default_namespace", &self.client.default_namespace())
+            .finish()
+    }
+}
+
+impl MetricsClient {
+    pub fn new(client: kube::Client) -> Self {
+        Self { client }
+    }
+
+    /// Underlying `kube::Client`
+    ///
+    pub fn client(&self) -> &kube::Client {
+        &self.client
+    }
+
+    /// Metrics of pod `name` in `namespace`
+    ///
+    pub async fn pod(&self, namespace: &str, name: &str) -> Result<PodMetrics, MetricsError> {
+        api::Api::namespaced(self.client.clone(), namespace)
+            .get(name)
+            .await
+            .map_err(MetricsError::from_kube)
+    }
+
+    /// Metrics of pods in `namespace`, optionally filtered by label `selector`
+    ///
+    pub async fn pods(
+        &self,
+        namespace: &str,
+        selector: Option<&str>,
+    ) -> Result<Vec<PodMetrics>, MetricsError> {
+        list(
+            api::Api::namespaced(self.client.clone(), namespace),
+            selector,
+        )
+        .await
+    }
+
+    /// Metrics of pods in all namespaces
+    ///
+    pub async fn all_pods(&self) -> Result<Vec<PodMetrics>, MetricsError> {
+        self.all_pods_with_selector(None).await
+    }
+
+    /// Metrics of pods in all namespaces, optionally filtered by label `selector`
+    ///
+    pub async fn all_pods_with_selector(
+        &self,
+        selector: Option<&str>,
+    ) -> Result<Vec<PodMetrics>, MetricsError> {
+        list(api::Api::all(self.client.clone()), selector).await
+    }
+
+    /// Metrics of node `name`
+    ///
+    pub async fn node(&self, name: &str) -> Result<NodeMetrics, MetricsError> {
+        api::Api::all(self.client.clone())
+            .get(name)
+            .await
+            .map_err(MetricsError::from_kube)
+    }
+
+    /// Metrics of nodes, optionally filtered by label `selector`
+    ///
+    pub async fn nodes(&self, selector: Option<&str>) -> Result<Vec<NodeMetrics>, MetricsError> {
+        list(api::Api::all(self.client.clone()), selector).await
+    }
+}
+
+impl From<kube::Client> for MetricsClient {
+    fn from(client: kube::Client) -> Self {
+        Self::new(client)
+    }
+}
+
+pub(crate) async fn list<K>(
+    api: api::Api<K>,
+    selector: Option<&str>,
+) -> Result<Vec<K>, MetricsError>
+where
+    K: Clone + fmt::Debug + serde::de::DeserializeOwned + kube::Resource,
+{
+    let lp = api::ListParams::default();
+    let lp = match selector {
+        Some(selector) => lp.labels(selector),
+        None => lp,
+    };
+    api.list(&lp)
+        .await
+        .map(|list| list.items)
+        .map_err(MetricsError::from_kube)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetricsError {
+    /// `metrics.k8s.io` API is not served, usually metrics-server is not installed
+    ///
+    #[error("Metrics API not available, is metrics-server installed? ({0})")]
+    NotInstalled(#[source] kube::Error),
+
+    /// `metrics.k8s.io` API is registered, but its backend is not ready to serve requests
+    ///
+    #[error("Metrics API not ready yet ({0})")]
+    NotReady(#[source] kube::Error),
+
+    /// Metrics of the requested object are not (yet) available
+    ///
+    #[error("Metrics not found ({0})")]
+    NotFound(#[source] kube::Error),
+
+    #[error(transparent)]
+    Kube(kube::Error),
+}
+
+impl MetricsError {
+    fn from_kube(error: kube::Error) -> Self {
+        match &error {
+            kube::Error::Api(status) if status.code == 503 => Self::NotReady(error),
+            kube::Error::Api(status) if status.code == 404 => {
+                let named = status
+                    .details
+                    .as_ref()
+                    .is_some_and(|details| !details.name.is_empty());
+                if named {
+                    Self::NotFound(error)
+                } else {
+                    Self::NotInstalled(error)
+                }
+            }
+            _ => Self::Kube(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kube::client::Body;
+    use tower_test::mock;
+
+    use super::*;
+
+    type Handle = mock::Handle<http::Request<Body>, http::Response<Body>>;
+
+    const POD: &str = r#"{
+  "kind": "PodMetrics",
+  "apiVersion": "metrics.k8s.io/v1beta1",
+  "metadata": {"name": "web-0", "namespace": "default"},
+  "timestamp": "2022-10-09T11:51:20Z",
+  "window": "15s",
+  "containers": [{"name": "nginx", "usage": {"cpu": "250m", "memory": "64Mi"}}]
+}"#;
+
+    const NODES: &str = r#"{
+  "kind": "NodeMetricsList",
+  "apiVersion": "metrics.k8s.io/v1beta1",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "docker-desktop"},
+      "timestamp": "2022-10-09T11:41:45Z",
+      "window": "23.5s",
+      "usage": {"cpu": "196382978n", "memory": "1848836Ki"}
+    }
+  ]
+}"#;
+
+    const NOT_INSTALLED: &str = r#"{
+  "kind": "Status",
+  "apiVersion": "v1",
+  "metadata": {},
+  "status": "Failure",
+  "message": "the server could not find the requested resource",
+  "reason": "NotFound",
+  "details": {},
+  "code": 404
+}"#;
+
+    const NOT_FOUND: &str = r#"{
+  "kind": "Status",
+  "apiVersion": "v1",
+  "metadata": {},
+  "status": "Failure",
+  "message": "podmetrics.metrics.k8s.io \"web-1\" not found",
+  "reason": "NotFound",
+  "details": {"name": "web-1", "group": "metrics.k8s.io", "kind": "pods"},
+  "code": 404
+}"#;
+
+    const NOT_READY: &str = r#"{
+  "kind": "Status",
+  "apiVersion": "v1",
+  "metadata": {},
+  "status": "Failure",
+  "message": "the server is currently unable to handle the request",
+  "reason": "ServiceUnavailable",
+  "code": 503
+}"#;
+
+    fn mock_client() -> (MetricsClient, Handle) {
+        let (service, handle) = mock::pair::<http::Request<Body>, http::Response<Body>>();
+        let client = kube::Client::new(service, "default");
+        (MetricsClient::new(client), handle)
+    }
+
+    fn serve(mut handle: Handle, path_and_query: &'static str, status: u16, body: &'static str) {
+        tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.expect("request expected");
+            assert_eq!(request.uri().path_and_query().unwrap(), path_and_query);
+            let response = http::Response::builder()
+                .status(status)
+                .body(Body::from(body.as_bytes().to_vec()))
+                .unwrap();
+            send.send_response(response);
+        });
+    }
+
+    #[tokio::test]
+    async fn pod() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/namespaces/default/pods/web-0",
+            200,
+            POD,
+        );
+
+        let pod = client.pod("default", "web-0").await.unwrap();
+        assert_eq!(pod.metadata.name.as_deref(), Some("web-0"));
+        assert_eq!(pod.cpu().unwrap(), Cpu::from_millicores(250));
+    }
+
+    #[tokio::test]
+    async fn pods_with_selector() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/namespaces/web/pods?&labelSelector=app%3Dweb",
+            200,
+            r#"{"kind":"PodMetricsList","apiVersion":"metrics.k8s.io/v1beta1","metadata":{},"items":[]}"#,
+        );
+
+        let pods = client.pods("web", Some("app=web")).await.unwrap();
+        assert!(pods.is_empty());
+    }
+
+    #[tokio::test]
+    async fn all_pods() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/pods?",
+            200,
+            r#"{"kind":"PodMetricsList","apiVersion":"metrics.k8s.io/v1beta1","metadata":{},"items":[]}"#,
+        );
+
+        client.all_pods().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn nodes() {
+        let (client, handle) = mock_client();
+        serve(handle, "/apis/metrics.k8s.io/v1beta1/nodes?", 200, NODES);
+
+        let nodes = client.nodes(None).await.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].memory().unwrap(), Memory::from_kib(1848836));
+    }
+
+    #[tokio::test]
+    async fn not_installed() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/nodes?",
+            404,
+            NOT_INSTALLED,
+        );
+
+        let err = client.nodes(None).await.unwrap_err();
+        assert!(matches!(err, MetricsError::NotInstalled(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/namespaces/default/pods/web-1",
+            404,
+            NOT_FOUND,
+        );
+
+        let err = client.pod("default", "web-1").await.unwrap_err();
+        assert!(matches!(err, MetricsError::NotFound(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn not_ready() {
+        let (client, handle) = mock_client();
+        serve(
+            handle,
+            "/apis/metrics.k8s.io/v1beta1/nodes/docker-desktop",
+            503,
+            NOT_READY,
+        );
+
+        let err = client.node("docker-desktop").await.unwrap_err();
+        assert!(matches!(err, MetricsError::NotReady(_)), "{err:?}");
+    }
+}
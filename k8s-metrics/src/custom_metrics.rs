@@ -1,5 +1,6 @@
 use super::*;
 
+pub mod v1beta1;
 pub mod v1beta2;
 
 pub trait CustomMetric {
@@ -7,3 +8,172 @@ pub trait CustomMetric {
     const URL_PATH_SEGMENT: &'static str;
     type Scope: k8s::ResourceScope;
 }
+
+/// Placeholder for the described kind of `MetricValue`s whose kind is only known at runtime,
+/// e.g. in an adapter serving metrics of arbitrary resources
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnyKind;
+
+impl k8s::Resource for AnyKind {
+    const API_VERSION: &'static str = "";
+    const GROUP: &'static str = "";
+    const KIND: &'static str = "";
+    const VERSION: &'static str = "";
+    const URL_PATH_SEGMENT: &'static str = "";
+    type Scope = AnyScope;
+}
+
+/// Scope of `AnyKind`, described objects may be namespaced or cluster scoped
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnyScope;
+
+impl k8s::ResourceScope for AnyScope {}
+
+impl k8s::ListableResource for AnyKind {
+    const LIST_KIND: &'static str = "";
+}
+
+/// Custom metric advertised by a metrics adapter in API discovery
+///
+/// Adapters list their metrics as `{resource}/{metric}` entries of the
+/// `APIResourceList` served at `/apis/custom.metrics.k8s.io/{version}`,
+/// e.g. `pods/http_requests` or `namespaces/queue_length`.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CustomMetricInfo {
+    /// Resource of described objects, e.g. `pods`
+    ///
+    pub resource: String,
+    /// Metric name
+    ///
+    pub metric: String,
+    /// Described objects are namespaced
+    ///
+    pub namespaced: bool,
+}
+
+impl CustomMetricInfo {
+    /// Metrics advertised in `list`, entries not of the form `{resource}/{metric}` are skipped
+    ///
+    pub fn from_api_resources(list: &metav1::APIResourceList) -> Vec<Self> {
+        list.resources
+            .iter()
+            .filter_map(|resource| {
+                let (resource_name, metric) = resource.name.split_once('/')?;
+                Some(Self {
+                    resource: resource_name.to_string(),
+                    metric: metric.to_string(),
+                    namespaced: resource.namespaced,
+                })
+            })
+            .collect()
+    }
+
+    /// Entry advertising this metric in `APIResourceList`
+    ///
+    pub fn api_resource(&self) -> metav1::APIResource {
+        metav1::APIResource {
+            name: format!("{}/{}", self.resource, self.metric),
+            namespaced: self.namespaced,
+            kind: "MetricValueList".to_string(),
+            verbs: vec!["get".to_string()],
+            ..default()
+        }
+    }
+
+    /// This metric describes objects of kind `K`
+    ///
+    pub fn describes<K: k8s::Resource>(&self) -> bool {
+        self.resource == K::URL_PATH_SEGMENT
+    }
+
+    /// This is the metric `C`
+    ///
+    pub fn is<C: CustomMetric>(&self) -> bool {
+        self.metric == C::URL_PATH_SEGMENT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s::serde_json as json;
+
+    use super::*;
+
+    const API_RESOURCES: &str = r#"{
+  "kind": "APIResourceList",
+  "apiVersion": "v1",
+  "groupVersion": "custom.metrics.k8s.io/v1beta2",
+  "resources": [
+    {"name": "pods/http_requests", "singularName": "", "namespaced": true, "kind": "MetricValueList", "verbs": ["get"]},
+    {"name": "namespaces/http_requests", "singularName": "", "namespaced": false, "kind": "MetricValueList", "verbs": ["get"]},
+    {"name": "nodes/node_load1", "singularName": "", "namespaced": false, "kind": "MetricValueList", "verbs": ["get"]}
+  ]
+}"#;
+
+    struct HttpRequests;
+
+    impl CustomMetric for HttpRequests {
+        const KIND: &'static str = "MetricValue";
+        const URL_PATH_SEGMENT: &'static str = "http_requests";
+        type Scope = k8s::NamespaceResourceScope;
+    }
+
+    #[test]
+    fn from_api_resources() {
+        let list = json::from_str::<metav1::APIResourceList>(API_RESOURCES).unwrap();
+        let metrics = CustomMetricInfo::from_api_resources(&list);
+        assert_eq!(
+            metrics[0],
+            CustomMetricInfo {
+                resource: "pods".to_string(),
+                metric: "http_requests".to_string(),
+                namespaced: true,
+            }
+        );
+        assert_eq!(metrics.len(), 3);
+
+        let pods = metrics
+            .iter()
+            .filter(|metric| metric.describes::<corev1::Pod>())
+            .collect::<Vec<_>>();
+        assert_eq!(pods, [&metrics[0]]);
+
+        let node = &metrics[2];
+        assert!(node.describes::<corev1::Node>());
+        assert_eq!(node.metric, "node_load1");
+        assert!(!node.namespaced);
+
+        let http_requests = metrics
+            .iter()
+            .filter(|metric| metric.is::<HttpRequests>())
+            .count();
+        assert_eq!(http_requests, 2);
+    }
+
+    #[test]
+    fn skip_malformed() {
+        let list = metav1::APIResourceList {
+            group_version: "custom.metrics.k8s.io/v1beta2".to_string(),
+            resources: vec![metav1::APIResource {
+                name: "http_requests".to_string(),
+                ..default()
+            }],
+        };
+        assert!(CustomMetricInfo::from_api_resources(&list).is_empty());
+    }
+
+    #[test]
+    fn api_resource() {
+        let list = json::from_str::<metav1::APIResourceList>(API_RESOURCES).unwrap();
+        for resource in &list.resources {
+            let info = CustomMetricInfo::from_api_resources(&metav1::APIResourceList {
+                group_version: list.group_version.clone(),
+                resources: vec![resource.clone()],
+            });
+            assert_eq!(&info[0].api_resource(), resource);
+        }
+    }
+}
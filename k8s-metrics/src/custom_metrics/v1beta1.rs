@@ -0,0 +1,188 @@
+use std::marker::PhantomData;
+
+use constcat::concat;
+
+use super::*;
+
+const VERSION: &str = "v1beta1";
+
+/// `MetricValue` is the metric value for some object
+///
+/// Unlike `v1beta2::MetricValue` the metric is identified by `metric_name`
+/// and `selector` directly, rather than by a nested `MetricIdentifier`.
+///
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricValue<M> {
+    /// not part of the wire format, items of `MetricValueList` only carry `TypeMeta`
+    ///
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub metadata: metav1::ObjectMeta,
+
+    /// a reference to the described object
+    ///
+    pub described_object: corev1::ObjectReference, // `json:"describedObject" protobuf:"bytes,1,name=describedObject"`
+
+    /// the name of the metric
+    ///
+    pub metric_name: String, // `json:"metricName" protobuf:"bytes,2,name=metricName"`
+
+    /// indicates the time at which the metrics were produced
+    ///
+    pub timestamp: metav1::Time, // `json:"timestamp" protobuf:"bytes,3,name=timestamp"`
+
+    /// indicates the window ([Timestamp-Window, Timestamp]) from
+    /// which these metrics were calculated, when returning rate
+    /// metrics calculated from cumulative metrics (or zero for
+    /// non-calculated instantaneous metrics).
+    ///
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub window_seconds: i64, // `json:"windowSeconds,omitempty" protobuf:"bytes,4,opt,name=windowSeconds"`
+
+    /// the value of the metric for this
+    ///
+    pub value: resource::Quantity, // `json:"value" protobuf:"bytes,5,name=value"`
+
+    /// selector represents the label selector that could be used to select
+    /// this metric, and will generally just be the selector passed in to
+    /// the query used to fetch this metric.
+    /// When left blank, only the metric's Name will be used to gather metrics.
+    /// +optional
+    ///
+    pub selector: Option<metav1::LabelSelector>, // `json:"selector" protobuf:"bytes,6,opt,name=selector"`
+
+    #[serde(skip)]
+    pub phantom: PhantomData<M>,
+}
+
+impl<M: k8s::Resource> k8s::Resource for MetricValue<M> {
+    const API_VERSION: &'static str = concat!(CUSTOM_METRICS_API_GROUP, "/", VERSION);
+    const GROUP: &'static str = CUSTOM_METRICS_API_GROUP;
+    const KIND: &'static str = "MetricValue";
+    const VERSION: &'static str = VERSION;
+    const URL_PATH_SEGMENT: &'static str = M::URL_PATH_SEGMENT;
+    type Scope = M::Scope;
+}
+
+impl<M: k8s::Metadata> k8s::Metadata for MetricValue<M> {
+    type Ty = metav1::ObjectMeta;
+
+    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut <Self as k8s_openapi::Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl<M: k8s::ListableResource> k8s::ListableResource for MetricValue<M> {
+    const LIST_KIND: &'static str = "MetricValueList";
+}
+
+pub type MetricValueList<M> = k8s::List<MetricValue<M>>;
+
+impl<M> From<v1beta2::MetricValue<M>> for MetricValue<M> {
+    fn from(value: v1beta2::MetricValue<M>) -> Self {
+        Self {
+            metadata: value.metadata,
+            described_object: value.described_object,
+            metric_name: value.metric.name,
+            timestamp: value.timestamp,
+            window_seconds: value.window_seconds,
+            value: value.value,
+            selector: value.metric.selector,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M> From<MetricValue<M>> for v1beta2::MetricValue<M> {
+    fn from(value: MetricValue<M>) -> Self {
+        let metric = v1beta2::MetricIdentifier {
+            name: value.metric_name,
+            selector: value.selector,
+        };
+        Self {
+            metadata: value.metadata,
+            described_object: value.described_object,
+            metric,
+            timestamp: value.timestamp,
+            window_seconds: value.window_seconds,
+            value: value.value,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s::serde_json as json;
+
+    use super::*;
+
+    const GOLDEN_PODS: &str = include_str!("../../testdata/custom-metrics-v1beta1-pods.json");
+
+    #[test]
+    fn golden_deserialize() {
+        let list = json::from_str::<MetricValueList<corev1::Pod>>(GOLDEN_PODS).unwrap();
+        assert_eq!(list.items.len(), 2);
+
+        let first = &list.items[0];
+        assert_eq!(first.metric_name, "http_requests");
+        assert!(first.selector.is_none());
+        assert_eq!(first.window_seconds, 60);
+        assert_eq!(first.value, resource::Quantity("66m".to_string()));
+
+        let selector = list.items[1].selector.as_ref().unwrap();
+        assert_eq!(selector.match_labels.as_ref().unwrap()["app"], "sample-app");
+    }
+
+    #[test]
+    fn golden_roundtrip() {
+        let list = json::from_str::<MetricValueList<corev1::Pod>>(GOLDEN_PODS).unwrap();
+        let golden = json::from_str::<json::Value>(GOLDEN_PODS).unwrap();
+        assert_eq!(json::to_value(&list).unwrap(), golden);
+    }
+
+    #[test]
+    fn into_v1beta2() {
+        let list = json::from_str::<MetricValueList<corev1::Pod>>(GOLDEN_PODS).unwrap();
+        let items = list
+            .items
+            .into_iter()
+            .map(v1beta2::MetricValue::from)
+            .collect::<Vec<_>>();
+        let list = v1beta2::MetricValueList {
+            items,
+            metadata: default(),
+        };
+
+        let golden = include_str!("../../testdata/custom-metrics-v1beta2-pods.json");
+        let golden = json::from_str::<json::Value>(golden).unwrap();
+        assert_eq!(json::to_value(&list).unwrap(), golden);
+    }
+
+    #[test]
+    fn lossless() {
+        let golden = include_str!("../../testdata/custom-metrics-v1beta2-pods.json");
+        let v1beta2 = json::from_str::<v1beta2::MetricValueList<corev1::Pod>>(golden).unwrap();
+        let roundtrip = v1beta2
+            .items
+            .into_iter()
+            .map(MetricValue::from)
+            .map(v1beta2::MetricValue::from)
+            .collect::<Vec<_>>();
+        let roundtrip = v1beta2::MetricValueList {
+            items: roundtrip,
+            metadata: default(),
+        };
+        let golden = json::from_str::<json::Value>(golden).unwrap();
+        assert_eq!(json::to_value(&roundtrip).unwrap(), golden);
+
+        let value = v1beta2::MetricValue::<corev1::Pod>::new("http_requests", "default", "web-0");
+        let metadata = value.metadata.clone();
+        let value = v1beta2::MetricValue::from(MetricValue::from(value));
+        assert_eq!(value.metadata, metadata);
+    }
+}
@@ -1,5 +1,7 @@
 use std::marker::PhantomData;
 
+use constcat::concat;
+
 use super::*;
 
 /// `MetricIdentifier` identifies a metric by name and, optionally, selector
@@ -15,7 +17,6 @@ pub struct MetricIdentifier {
     /// When left blank, only the metric's Name will be used to gather metrics.
     /// +optional
     ///
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub selector: Option<metav1::LabelSelector>,
 }
 
@@ -30,8 +31,11 @@ impl MetricIdentifier {
 /// `MetricValue` is the metric value for some object
 ///
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(rename = "camelCase")]
+#[serde(rename_all = "camelCase")]
 pub struct MetricValue<M> {
+    /// not part of the wire format, items of `MetricValueList` only carry `TypeMeta`
+    ///
+    #[serde(default, skip_serializing_if = "is_default")]
     pub metadata: metav1::ObjectMeta,
 
     /// a reference to the described object
@@ -49,6 +53,7 @@ pub struct MetricValue<M> {
     /// metrics calculated from cumulative metrics (or zero for
     /// non-calculated instantaneous metrics).
     ///
+    #[serde(default, skip_serializing_if = "is_default")]
     pub window_seconds: i64, // `json:"windowSeconds,omitempty" protobuf:"bytes,4,opt,name=windowSeconds"`
 
     /// the value of the metric for this
@@ -60,10 +65,11 @@ pub struct MetricValue<M> {
 }
 
 impl<M: k8s::Resource> k8s::Resource for MetricValue<M> {
-    const API_VERSION: &'static str = "custom.metrics.k8s.io/v1beta2";
-    const GROUP: &'static str = "custom.metrics.k8s.io";
-    const KIND: &'static str = M::KIND;
-    const VERSION: &'static str = "v1beta2";
+    const API_VERSION: &'static str =
+        concat!(CUSTOM_METRICS_API_GROUP, "/", CUSTOM_METRICS_API_VERSION);
+    const GROUP: &'static str = CUSTOM_METRICS_API_GROUP;
+    const KIND: &'static str = "MetricValue";
+    const VERSION: &'static str = CUSTOM_METRICS_API_VERSION;
     const URL_PATH_SEGMENT: &'static str = M::URL_PATH_SEGMENT;
     type Scope = M::Scope;
 }
@@ -120,6 +126,15 @@ where
         }
     }
 
+    /// Create `MetricValue` describing `object`
+    ///
+    pub fn with_object(name: impl ToString, object: &M) -> Self {
+        let object_ref = object_ref(object);
+        Self::with_object_ref(name, &object_ref)
+    }
+}
+
+impl<M> MetricValue<M> {
     /// Create `MetricValue` describing object by its `corev1::ObjectReference`
     ///
     pub fn with_object_ref(name: impl ToString, object_ref: &corev1::ObjectReference) -> Self {
@@ -145,19 +160,45 @@ where
         }
     }
 
-    /// Create `MetricValue` describing `object`
-    ///
-    pub fn with_object(name: impl ToString, object: &M) -> Self {
-        let object_ref = object_ref(object);
-        Self::with_object_ref(name, &object_ref)
-    }
-
     /// Set timestamp for this `MetricValue`
     ///
     pub fn timestamp(self, timestamp: Timestamp) -> Self {
         let timestamp = metav1::Time(timestamp);
         Self { timestamp, ..self }
     }
+
+    /// Set timestamp of this `MetricValue` to current time
+    ///
+    pub fn now(self) -> Self {
+        self.timestamp(Timestamp::now())
+    }
+
+    /// Set value of this `MetricValue`
+    ///
+    pub fn value(self, value: impl IntoQuantity) -> Self {
+        let value = value.into_quantity();
+        Self { value, ..self }
+    }
+
+    /// Set window the value was calculated over, truncated to whole seconds
+    ///
+    pub fn window(self, window: time::Duration) -> Self {
+        let window_seconds = window.as_secs() as i64;
+        Self {
+            window_seconds,
+            ..self
+        }
+    }
+
+    /// Set label selector of the metric
+    ///
+    pub fn selector(self, selector: metav1::LabelSelector) -> Self {
+        let metric = MetricIdentifier {
+            selector: Some(selector),
+            ..self.metric
+        };
+        Self { metric, ..self }
+    }
 }
 
 impl<M: k8s::ListableResource> k8s::ListableResource for MetricValue<M> {
@@ -166,6 +207,161 @@ impl<M: k8s::ListableResource> k8s::ListableResource for MetricValue<M> {
 
 pub type MetricValueList<M> = k8s::List<MetricValue<M>>;
 
+/// Request for custom metric values describing objects of kind `M`
+///
+/// Builds one of
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/{resource}/{name}/{metric}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/{resource}/*/{metric}?labelSelector={selector}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/metrics/{metric}`
+///
+/// or their cluster scoped counterparts without the `namespaces/{namespace}` part,
+/// optionally with `metricLabelSelector={metric_selector}`. Requests for namespaced kinds
+/// take the namespace in their constructors.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricValueRequest<M> {
+    namespace: Option<String>,
+    target: Target,
+    metric: String,
+    selector: Option<String>,
+    metric_selector: Option<String>,
+    phantom: PhantomData<M>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Target {
+    Object(String),
+    All,
+    /// The namespace itself, so the namespace is never missing from the path
+    ///
+    Namespace(String),
+}
+
+impl<M: k8s::Resource<Scope = k8s::ClusterResourceScope>> MetricValueRequest<M> {
+    /// Request `metric` of cluster scoped object `name`
+    ///
+    pub fn object(name: impl ToString, metric: impl ToString) -> Self {
+        Self::with_target(None, Target::Object(name.to_string()), metric)
+    }
+
+    /// Request `metric` of all cluster scoped objects, usually narrowed down by `selector()`
+    ///
+    pub fn all(metric: impl ToString) -> Self {
+        Self::with_target(None, Target::All, metric)
+    }
+}
+
+impl<M: k8s::Resource<Scope = k8s::NamespaceResourceScope>> MetricValueRequest<M> {
+    /// Request `metric` of object `name` in `namespace`
+    ///
+    pub fn namespaced_object(
+        namespace: impl ToString,
+        name: impl ToString,
+        metric: impl ToString,
+    ) -> Self {
+        let target = Target::Object(name.to_string());
+        Self::with_target(Some(namespace.to_string()), target, metric)
+    }
+
+    /// Request `metric` of all objects in `namespace`, usually narrowed down by `selector()`
+    ///
+    pub fn namespaced_all(namespace: impl ToString, metric: impl ToString) -> Self {
+        Self::with_target(Some(namespace.to_string()), Target::All, metric)
+    }
+}
+
+impl<M: k8s::Resource> MetricValueRequest<M> {
+    /// Set label selector of described objects
+    ///
+    pub fn selector(self, selector: impl ToString) -> Self {
+        let selector = Some(selector.to_string());
+        Self { selector, ..self }
+    }
+
+    /// Set label selector of the metric series
+    ///
+    pub fn metric_selector(self, metric_selector: impl ToString) -> Self {
+        let metric_selector = Some(metric_selector.to_string());
+        Self {
+            metric_selector,
+            ..self
+        }
+    }
+
+    /// Path and query of this request
+    ///
+    pub fn uri(&self) -> String {
+        let mut path = format!("/apis/{}", <MetricValue<M> as k8s::Resource>::API_VERSION);
+        let namespace = match &self.target {
+            Target::Namespace(namespace) => Some(namespace),
+            _ => self.namespace.as_ref(),
+        };
+        if let Some(namespace) = namespace {
+            path = format!("{path}/namespaces/{}", request::segment(namespace));
+        }
+        let metric = request::segment(&self.metric);
+        let path = match &self.target {
+            Target::Object(name) => format!(
+                "{path}/{}/{}/{metric}",
+                M::URL_PATH_SEGMENT,
+                request::segment(name)
+            ),
+            Target::All => format!("{path}/{}/*/{metric}", M::URL_PATH_SEGMENT),
+            Target::Namespace(_) => format!("{path}/metrics/{metric}"),
+        };
+        request::uri(&path, &self.query())
+    }
+
+    /// Build the `http::Request`
+    ///
+    pub fn request(&self) -> Result<http::Request<Vec<u8>>, http::Error> {
+        request::get(&self.uri())
+    }
+
+    fn with_target(namespace: Option<String>, target: Target, metric: impl ToString) -> Self {
+        Self {
+            namespace,
+            target,
+            metric: metric.to_string(),
+            selector: None,
+            metric_selector: None,
+            phantom: PhantomData,
+        }
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        let selector = self
+            .selector
+            .as_deref()
+            .map(|selector| ("labelSelector", selector));
+        let metric_selector = self
+            .metric_selector
+            .as_deref()
+            .map(|selector| ("metricLabelSelector", selector));
+        selector.into_iter().chain(metric_selector).collect()
+    }
+}
+
+impl<M: k8s::ListableResource> MetricValueRequest<M> {
+    /// Parse response to this request
+    ///
+    pub fn parse_response(
+        &self,
+        status: http::StatusCode,
+        body: &[u8],
+    ) -> Result<MetricValueList<M>, ResponseError> {
+        request::parse(status, body)
+    }
+}
+
+impl MetricValueRequest<corev1::Namespace> {
+    /// Request `metric` describing the `namespace` itself
+    ///
+    pub fn namespace_metric(namespace: impl ToString, metric: impl ToString) -> Self {
+        Self::with_target(None, Target::Namespace(namespace.to_string()), metric)
+    }
+}
+
 fn object_ref<K>(object: &K) -> corev1::ObjectReference
 where
     K: k8s::Metadata<Ty = metav1::ObjectMeta>,
@@ -183,6 +379,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use k8s::serde_json as json;
     use k8s::Resource as _;
 
     use super::*;
@@ -472,4 +669,173 @@ mod tests {
         assert_eq!(metric_value.metadata.name.unwrap(), "requests_per_minute");
         assert_eq!(metric_value.timestamp, metav1::Time(timestamp));
     }
+
+    const GOLDEN_PODS: &str = include_str!("../../testdata/custom-metrics-v1beta2-pods.json");
+
+    #[test]
+    fn golden_deserialize() {
+        let list = json::from_str::<MetricValueList<corev1::Pod>>(GOLDEN_PODS).unwrap();
+        assert_eq!(list.items.len(), 2);
+
+        let first = &list.items[0];
+        assert_eq!(
+            first.described_object.name.as_deref(),
+            Some("sample-app-7cf6b8d5b4-9xkzb")
+        );
+        assert_eq!(first.described_object.kind.as_deref(), Some("Pod"));
+        assert_eq!(first.metric.name, "http_requests");
+        assert!(first.metric.selector.is_none());
+        assert_eq!(first.window_seconds, 60);
+        assert_eq!(first.value, resource::Quantity("66m".to_string()));
+        assert_eq!(
+            first.timestamp.0,
+            "2024-03-18T09:21:43Z".parse::<Timestamp>().unwrap()
+        );
+
+        let second = &list.items[1];
+        assert_eq!(second.window_seconds, 0);
+        let selector = second.metric.selector.as_ref().unwrap();
+        assert_eq!(selector.match_labels.as_ref().unwrap()["app"], "sample-app");
+    }
+
+    #[test]
+    fn golden_roundtrip() {
+        let list = json::from_str::<MetricValueList<corev1::Pod>>(GOLDEN_PODS).unwrap();
+        let golden = json::from_str::<json::Value>(GOLDEN_PODS).unwrap();
+        assert_eq!(json::to_value(&list).unwrap(), golden);
+    }
+
+    #[test]
+    fn serialize_camel_case() {
+        let value = MetricValue::<corev1::Pod>::new("http_requests", "default", "web-0");
+        let value = json::to_value(&value).unwrap();
+        let object = value.as_object().unwrap();
+        assert!(object.contains_key("describedObject"));
+        assert!(!object.contains_key("described_object"));
+        assert!(!object.contains_key("windowSeconds"));
+        assert!(!object.contains_key("phantom"));
+    }
+
+    #[test]
+    fn metric_value_kind() {
+        assert_eq!(MetricValue::<corev1::Pod>::KIND, "MetricValue");
+        assert_eq!(MetricValue::<corev1::Pod>::URL_PATH_SEGMENT, "pods");
+    }
+
+    #[test]
+    fn request_object() {
+        let request = MetricValueRequest::<corev1::Pod>::namespaced_object(
+            "default",
+            "web-0",
+            "http_requests",
+        )
+        .request()
+        .unwrap();
+        assert_eq!(request.method(), http::Method::GET);
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/web-0/http_requests"
+        );
+    }
+
+    #[test]
+    fn request_selector() {
+        let request = MetricValueRequest::<corev1::Pod>::namespaced_all("default", "http_requests")
+            .selector("app=sample-app")
+            .metric_selector("verb=GET");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/*/http_requests?labelSelector=app%3Dsample-app&metricLabelSelector=verb%3DGET"
+        );
+    }
+
+    #[test]
+    fn request_escaped() {
+        let request =
+            MetricValueRequest::<corev1::Pod>::namespaced_object("default", "web/0", "requests?%#")
+                .metric_selector("path=/index.html");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/web%2F0/requests%3F%25%23?metricLabelSelector=path%3D%2Findex.html"
+        );
+    }
+
+    #[test]
+    fn request_cluster_scoped() {
+        let request = MetricValueRequest::<corev1::Node>::object("node-1", "temperature");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/nodes/node-1/temperature"
+        );
+    }
+
+    #[test]
+    fn request_namespaced_pods() {
+        let request = MetricValueRequest::<corev1::Pod>::namespaced_object("web", "web-0", "m");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/web/pods/web-0/m"
+        );
+        let request = MetricValueRequest::<corev1::Pod>::namespaced_all("web", "m");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/web/pods/*/m"
+        );
+        let request = MetricValueRequest::<corev1::Node>::all("m");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/nodes/*/m"
+        );
+    }
+
+    #[test]
+    fn request_namespace_metric() {
+        let request =
+            MetricValueRequest::<corev1::Namespace>::namespace_metric("default", "queue_length");
+        assert_eq!(
+            request.uri(),
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/metrics/queue_length"
+        );
+    }
+
+    #[test]
+    fn parse_response() {
+        let request = MetricValueRequest::<corev1::Pod>::namespaced_all("default", "http_requests");
+        let list = request
+            .parse_response(http::StatusCode::OK, GOLDEN_PODS.as_bytes())
+            .unwrap();
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(list.items[1].value, resource::Quantity("1500m".to_string()));
+
+        let err = request
+            .parse_response(
+                http::StatusCode::NOT_FOUND,
+                br#"{"kind":"Status","apiVersion":"v1","metadata":{},"status":"Failure","message":"the server could not find the metric http_requests for pods","reason":"NotFound","code":404}"#,
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), Some(404));
+    }
+
+    #[test]
+    fn metric_value_builder() {
+        let selector = metav1::LabelSelector {
+            match_labels: Some([("app".to_string(), "web".to_string())].into()),
+            ..default()
+        };
+        let metric_value: MetricValue<corev1::Pod> =
+            MetricValue::new("http_requests", "default", "web-0")
+                .value(0.066)
+                .window(time::Duration::from_secs_f64(60.7))
+                .selector(selector.clone())
+                .now();
+
+        assert_eq!(metric_value.value, resource::Quantity("66m".to_string()));
+        assert_eq!(metric_value.window_seconds, 60);
+        assert_eq!(metric_value.metric.name, "http_requests");
+        assert_eq!(metric_value.metric.selector, Some(selector));
+        assert_ne!(metric_value.timestamp.0, Timestamp::default());
+
+        let metric_value = metric_value.value(3);
+        assert_eq!(metric_value.value, resource::Quantity("3".to_string()));
+    }
 }
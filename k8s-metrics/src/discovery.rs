@@ -0,0 +1,473 @@
+//! Discovery of metrics APIs served by the cluster
+//!
+//! Metrics APIs are served by aggregated API servers (metrics-server, prometheus-adapter, KEDA, ...).
+//! When such a server is not installed or not healthy, requests fail with rather vague errors,
+//! so it is useful to check upfront which metrics APIs can actually be used.
+//!
+use k8s::kube_aggregator::pkg::apis::apiregistration::v1::APIService;
+use kube::api;
+use kube::client::APIGroupDiscovery;
+
+use super::*;
+
+const FRESHNESS_STALE: &str = "Stale";
+const CONDITION_AVAILABLE: &str = "Available";
+
+/// Status of all metrics API groups
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricsApis {
+    /// `metrics.k8s.io`, usually served by metrics-server
+    ///
+    pub metrics: ApiGroupStatus,
+    /// `custom.metrics.k8s.io`
+    ///
+    pub custom_metrics: ApiGroupStatus,
+    /// `external.metrics.k8s.io`
+    ///
+    pub external_metrics: ApiGroupStatus,
+}
+
+/// Status of a single API group
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiGroupStatus {
+    pub group: String,
+    /// Versions served or registered, preferred version first
+    ///
+    pub versions: Vec<ApiVersionStatus>,
+}
+
+/// Status of a single version of an API group
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiVersionStatus {
+    pub version: String,
+    /// Version is listed in API discovery
+    ///
+    pub served: bool,
+    /// Discovery document of this version could not be refreshed by the API server
+    ///
+    pub stale: bool,
+    /// Availability as reported by the `APIService` of this version
+    ///
+    pub availability: Availability,
+}
+
+/// Availability of an `APIService`
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Availability {
+    /// `Available` condition is `True`
+    ///
+    Available,
+    /// `APIService` is missing or its `Available` condition is not `True`
+    ///
+    Unavailable {
+        reason: Option<String>,
+        message: Option<String>,
+    },
+    /// `APIService` could not be read, e.g. not permitted to
+    ///
+    Unknown,
+}
+
+impl MetricsApis {
+    /// Discover which metrics APIs are served by the cluster and whether they are available
+    ///
+    pub async fn discover(client: &kube::Client) -> Result<Self, kube::Error> {
+        let discovered = discover_groups(client).await?;
+        let services = api_services(client).await?;
+
+        let status = |group: &str| {
+            let discovered = discovered.iter().find(|discovered| {
+                discovered.metadata.as_ref().and_then(|m| m.name.as_deref()) == Some(group)
+            });
+            ApiGroupStatus::new(group, discovered, services.as_deref())
+        };
+
+        Ok(Self {
+            metrics: status(METRICS_API_GROUP),
+            custom_metrics: status(CUSTOM_METRICS_API_GROUP),
+            external_metrics: status(EXTERNAL_METRICS_API_GROUP),
+        })
+    }
+
+    /// Status of `group`, if it is one of the metrics API groups
+    ///
+    pub fn group(&self, group: &str) -> Option<&ApiGroupStatus> {
+        [&self.metrics, &self.custom_metrics, &self.external_metrics]
+            .into_iter()
+            .find(|status| status.group == group)
+    }
+}
+
+impl ApiGroupStatus {
+    fn new(
+        group: &str,
+        discovered: Option<&APIGroupDiscovery>,
+        services: Option<&[APIService]>,
+    ) -> Self {
+        let mut versions = discovered
+            .into_iter()
+            .flat_map(|discovered| &discovered.versions)
+            .filter_map(|version| {
+                let stale = version.freshness.as_deref() == Some(FRESHNESS_STALE);
+                version.version.clone().map(|version| ApiVersionStatus {
+                    version,
+                    served: true,
+                    stale,
+                    availability: Availability::Unknown,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Versions registered, but not served at all
+        let registered = services
+            .into_iter()
+            .flatten()
+            .filter_map(|service| service.spec.as_ref())
+            .filter(|spec| spec.group.as_deref() == Some(group))
+            .filter_map(|spec| spec.version.clone());
+        for version in registered {
+            if !versions.iter().any(|status| status.version == version) {
+                versions.push(ApiVersionStatus {
+                    version,
+                    served: false,
+                    stale: false,
+                    availability: Availability::Unknown,
+                });
+            }
+        }
+
+        if let Some(services) = services {
+            for status in &mut versions {
+                status.availability = Availability::of(group, &status.version, services);
+            }
+        }
+
+        Self {
+            group: group.to_string(),
+            versions,
+        }
+    }
+
+    /// Status of `version` of this group
+    ///
+    pub fn version(&self, version: &str) -> Option<&ApiVersionStatus> {
+        self.versions
+            .iter()
+            .find(|status| status.version == version)
+    }
+
+    /// Preferred version, if any is served
+    ///
+    pub fn preferred_version(&self) -> Option<&ApiVersionStatus> {
+        self.versions.iter().find(|status| status.served)
+    }
+
+    /// Group is served in `version` and can be used
+    ///
+    pub fn is_usable(&self, version: &str) -> bool {
+        self.version(version)
+            .is_some_and(ApiVersionStatus::is_usable)
+    }
+}
+
+impl ApiVersionStatus {
+    /// Version is served, and its `APIService` is available,
+    /// or if that is unknown, its discovery document is up to date
+    ///
+    pub fn is_usable(&self) -> bool {
+        match self.availability {
+            Availability::Available => self.served,
+            Availability::Unavailable { .. } => false,
+            Availability::Unknown => self.served && !self.stale,
+        }
+    }
+}
+
+impl Availability {
+    fn of(group: &str, version: &str, services: &[APIService]) -> Self {
+        let name = format!("{version}.{group}");
+        let Some(service) = services
+            .iter()
+            .find(|service| service.metadata.name.as_deref() == Some(name.as_str()))
+        else {
+            return Self::Unavailable {
+                reason: Some("NotRegistered".to_string()),
+                message: Some(format!("APIService {name} not found")),
+            };
+        };
+
+        let condition = service
+            .status
+            .iter()
+            .flat_map(|status| status.conditions.iter().flatten())
+            .find(|condition| condition.type_ == CONDITION_AVAILABLE);
+        match condition {
+            Some(condition) if condition.status == "True" => Self::Available,
+            Some(condition) => Self::Unavailable {
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+            },
+            None => Self::Unavailable {
+                reason: None,
+                message: None,
+            },
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        matches!(self, Self::Available)
+    }
+}
+
+/// Groups from aggregated discovery, falling back to legacy discovery
+/// for API servers that do not support aggregated discovery
+///
+async fn discover_groups(client: &kube::Client) -> Result<Vec<APIGroupDiscovery>, kube::Error> {
+    let aggregated = client.list_api_groups_aggregated().await?;
+    if !aggregated.items.is_empty() {
+        return Ok(aggregated.items);
+    }
+
+    let groups = client
+        .list_api_groups()
+        .await?
+        .groups
+        .into_iter()
+        .map(|group| APIGroupDiscovery {
+            metadata: Some(metav1::ObjectMeta {
+                name: Some(group.name),
+                ..default()
+            }),
+            versions: group
+                .versions
+                .into_iter()
+                .map(|version| kube::client::APIVersionDiscovery {
+                    version: Some(version.version),
+                    ..default()
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(groups)
+}
+
+/// All `APIService`s, or `None` if not permitted to list them
+///
+async fn api_services(client: &kube::Client) -> Result<Option<Vec<APIService>>, kube::Error> {
+    match api::Api::<APIService>::all(client.clone())
+        .list(&api::ListParams::default())
+        .await
+    {
+        Ok(list) => Ok(Some(list.items)),
+        Err(kube::Error::Api(status)) if matches!(status.code, 401 | 403 | 404) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kube::client::Body;
+    use tower_test::mock;
+
+    use super::*;
+
+    type Handle = mock::Handle<http::Request<Body>, http::Response<Body>>;
+
+    const AGGREGATED: &str = r#"{
+  "kind": "APIGroupDiscoveryList",
+  "apiVersion": "apidiscovery.k8s.io/v2",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "apps"},
+      "versions": [{"version": "v1", "resources": [], "freshness": "Current"}]
+    },
+    {
+      "metadata": {"name": "metrics.k8s.io"},
+      "versions": [{"version": "v1beta1", "resources": [], "freshness": "Current"}]
+    },
+    {
+      "metadata": {"name": "custom.metrics.k8s.io"},
+      "versions": [
+        {"version": "v1beta2", "resources": [], "freshness": "Stale"},
+        {"version": "v1beta1", "resources": [], "freshness": "Stale"}
+      ]
+    }
+  ]
+}"#;
+
+    const LEGACY: &str = r#"{
+  "kind": "APIGroupList",
+  "apiVersion": "v1",
+  "groups": [
+    {
+      "name": "metrics.k8s.io",
+      "versions": [{"groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1"}],
+      "preferredVersion": {"groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1"}
+    }
+  ]
+}"#;
+
+    const API_SERVICES: &str = r#"{
+  "kind": "APIServiceList",
+  "apiVersion": "apiregistration.k8s.io/v1",
+  "metadata": {},
+  "items": [
+    {
+      "metadata": {"name": "v1.apps"},
+      "spec": {"group": "apps", "version": "v1", "groupPriorityMinimum": 17800, "versionPriority": 15},
+      "status": {"conditions": [{"type": "Available", "status": "True", "reason": "Local"}]}
+    },
+    {
+      "metadata": {"name": "v1beta1.metrics.k8s.io"},
+      "spec": {"group": "metrics.k8s.io", "version": "v1beta1", "groupPriorityMinimum": 100, "versionPriority": 100},
+      "status": {"conditions": [{"type": "Available", "status": "True", "reason": "Passed"}]}
+    },
+    {
+      "metadata": {"name": "v1beta2.custom.metrics.k8s.io"},
+      "spec": {"group": "custom.metrics.k8s.io", "version": "v1beta2", "groupPriorityMinimum": 100, "versionPriority": 200},
+      "status": {
+        "conditions": [
+          {
+            "type": "Available",
+            "status": "False",
+            "reason": "FailedDiscoveryCheck",
+            "message": "failing or missing response from https://10.96.12.34:443/apis/custom.metrics.k8s.io/v1beta2"
+          }
+        ]
+      }
+    },
+    {
+      "metadata": {"name": "v1beta1.external.metrics.k8s.io"},
+      "spec": {"group": "external.metrics.k8s.io", "version": "v1beta1", "groupPriorityMinimum": 100, "versionPriority": 100},
+      "status": {"conditions": [{"type": "Available", "status": "False", "reason": "ServiceNotFound"}]}
+    }
+  ]
+}"#;
+
+    const FORBIDDEN: &str = r#"{
+  "kind": "Status",
+  "apiVersion": "v1",
+  "metadata": {},
+  "status": "Failure",
+  "message": "apiservices.apiregistration.k8s.io is forbidden",
+  "reason": "Forbidden",
+  "details": {"group": "apiregistration.k8s.io", "kind": "apiservices"},
+  "code": 403
+}"#;
+
+    fn mock_client() -> (kube::Client, Handle) {
+        let (service, handle) = mock::pair::<http::Request<Body>, http::Response<Body>>();
+        (kube::Client::new(service, "default"), handle)
+    }
+
+    async fn respond(handle: &mut Handle, path_and_query: &str, status: u16, body: &str) {
+        let (request, send) = handle.next_request().await.expect("request expected");
+        assert_eq!(request.uri().path_and_query().unwrap(), path_and_query);
+        let response = http::Response::builder()
+            .status(status)
+            .body(Body::from(body.as_bytes().to_vec()))
+            .unwrap();
+        send.send_response(response);
+    }
+
+    #[tokio::test]
+    async fn discover() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(&mut handle, "/apis", 200, AGGREGATED).await;
+            respond(
+                &mut handle,
+                "/apis/apiregistration.k8s.io/v1/apiservices?",
+                200,
+                API_SERVICES,
+            )
+            .await;
+        });
+
+        let apis = MetricsApis::discover(&client).await.unwrap();
+        server.await.unwrap();
+
+        assert!(apis.metrics.is_usable(METRICS_API_VERSION));
+        assert_eq!(
+            apis.metrics.preferred_version().unwrap().availability,
+            Availability::Available
+        );
+
+        let custom = &apis.custom_metrics;
+        assert!(!custom.is_usable(CUSTOM_METRICS_API_VERSION));
+        let v1beta2 = custom.version("v1beta2").unwrap();
+        assert!(v1beta2.served && v1beta2.stale);
+        assert_eq!(
+            v1beta2.availability,
+            Availability::Unavailable {
+                reason: Some("FailedDiscoveryCheck".to_string()),
+                message: Some("failing or missing response from https://10.96.12.34:443/apis/custom.metrics.k8s.io/v1beta2".to_string()),
+            }
+        );
+        let v1beta1 = custom.version("v1beta1").unwrap();
+        assert!(matches!(
+            &v1beta1.availability,
+            Availability::Unavailable { reason: Some(reason), .. } if reason == "NotRegistered"
+        ));
+
+        let external = &apis.external_metrics;
+        assert!(external.preferred_version().is_none());
+        let v1beta1 = external.version(EXTERNAL_METRICS_API_VERSION).unwrap();
+        assert!(!v1beta1.served);
+        assert!(!v1beta1.availability.is_available());
+
+        assert_eq!(apis.group(CUSTOM_METRICS_API_GROUP), Some(custom));
+        assert_eq!(apis.group("apps"), None);
+    }
+
+    #[tokio::test]
+    async fn api_services_forbidden() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(&mut handle, "/apis", 200, AGGREGATED).await;
+            respond(
+                &mut handle,
+                "/apis/apiregistration.k8s.io/v1/apiservices?",
+                403,
+                FORBIDDEN,
+            )
+            .await;
+        });
+
+        let apis = MetricsApis::discover(&client).await.unwrap();
+        server.await.unwrap();
+
+        let metrics = apis.metrics.version(METRICS_API_VERSION).unwrap();
+        assert_eq!(metrics.availability, Availability::Unknown);
+        assert!(metrics.is_usable());
+        assert!(!apis.custom_metrics.is_usable(CUSTOM_METRICS_API_VERSION));
+        assert!(apis.external_metrics.versions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn legacy_discovery() {
+        let (client, mut handle) = mock_client();
+        let server = tokio::spawn(async move {
+            respond(&mut handle, "/apis", 200, LEGACY).await;
+            respond(&mut handle, "/apis", 200, LEGACY).await;
+            respond(
+                &mut handle,
+                "/apis/apiregistration.k8s.io/v1/apiservices?",
+                200,
+                API_SERVICES,
+            )
+            .await;
+        });
+
+        let apis = MetricsApis::discover(&client).await.unwrap();
+        server.await.unwrap();
+
+        assert!(apis.metrics.is_usable(METRICS_API_VERSION));
+        assert!(!apis.metrics.version(METRICS_API_VERSION).unwrap().stale);
+    }
+}
@@ -2,7 +2,126 @@ use super::*;
 
 pub mod v1beta1;
 
+/// External metric served by `external.metrics.k8s.io`
+///
+/// External metrics are always namespaced and addressed by metric name,
+/// i.e. `/apis/external.metrics.k8s.io/v1beta1/namespaces/{namespace}/{URL_PATH_SEGMENT}`
+///
 pub trait ExternalMetric {
     const KIND: &'static str;
+    /// Metric name
+    ///
     const URL_PATH_SEGMENT: &'static str;
 }
+
+/// Placeholder for external metrics whose name is only known at runtime,
+/// e.g. in an adapter serving arbitrary metrics
+///
+/// Its `URL_PATH_SEGMENT` is empty, so the metric name must always be given explicitly.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnyMetric;
+
+impl ExternalMetric for AnyMetric {
+    const KIND: &'static str = "ExternalMetricValue";
+    const URL_PATH_SEGMENT: &'static str = "";
+}
+
+/// External metric advertised by a metrics adapter in API discovery
+///
+/// Adapters list their metrics by name in the `APIResourceList`
+/// served at `/apis/external.metrics.k8s.io/{version}`.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExternalMetricInfo {
+    /// Metric name
+    ///
+    pub metric: String,
+    /// Metric is served per namespace
+    ///
+    pub namespaced: bool,
+}
+
+impl ExternalMetricInfo {
+    /// Metrics advertised in `list`
+    ///
+    pub fn from_api_resources(list: &metav1::APIResourceList) -> Vec<Self> {
+        list.resources
+            .iter()
+            .filter(|resource| !resource.name.is_empty())
+            .map(|resource| Self {
+                metric: resource.name.clone(),
+                namespaced: resource.namespaced,
+            })
+            .collect()
+    }
+
+    /// Entry advertising this metric in `APIResourceList`
+    ///
+    pub fn api_resource(&self) -> metav1::APIResource {
+        metav1::APIResource {
+            name: self.metric.clone(),
+            namespaced: self.namespaced,
+            kind: "ExternalMetricValueList".to_string(),
+            verbs: vec!["get".to_string()],
+            ..default()
+        }
+    }
+
+    /// This is the metric `M`
+    ///
+    pub fn is<M: ExternalMetric>(&self) -> bool {
+        self.metric == M::URL_PATH_SEGMENT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s::serde_json as json;
+
+    use super::*;
+
+    const API_RESOURCES: &str = r#"{
+  "kind": "APIResourceList",
+  "apiVersion": "v1",
+  "groupVersion": "external.metrics.k8s.io/v1beta1",
+  "resources": [
+    {"name": "rabbitmq_queue_messages_ready", "singularName": "", "namespaced": true, "kind": "ExternalMetricValueList", "verbs": ["get"]},
+    {"name": "s0-rabbitmq-orders", "singularName": "", "namespaced": true, "kind": "ExternalMetricValueList", "verbs": ["get"]}
+  ]
+}"#;
+
+    struct Queue;
+
+    impl ExternalMetric for Queue {
+        const KIND: &'static str = "ExternalMetricValue";
+        const URL_PATH_SEGMENT: &'static str = "rabbitmq_queue_messages_ready";
+    }
+
+    #[test]
+    fn from_api_resources() {
+        let list = json::from_str::<metav1::APIResourceList>(API_RESOURCES).unwrap();
+        let metrics = ExternalMetricInfo::from_api_resources(&list);
+        assert_eq!(
+            metrics,
+            [
+                ExternalMetricInfo {
+                    metric: "rabbitmq_queue_messages_ready".to_string(),
+                    namespaced: true,
+                },
+                ExternalMetricInfo {
+                    metric: "s0-rabbitmq-orders".to_string(),
+                    namespaced: true,
+                },
+            ]
+        );
+        assert!(metrics[0].is::<Queue>());
+        assert!(!metrics[1].is::<Queue>());
+
+        let resources = metrics
+            .iter()
+            .map(ExternalMetricInfo::api_resource)
+            .collect::<Vec<_>>();
+        assert_eq!(resources, list.resources);
+    }
+}
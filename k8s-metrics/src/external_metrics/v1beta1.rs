@@ -1,6 +1,8 @@
 use std::collections::BTreeMap;
 use std::marker::PhantomData;
 
+use constcat::concat;
+
 use super::*;
 
 /// `ExternalMetricValue` is a metric value for external metric
@@ -9,8 +11,11 @@ use super::*;
 /// For one metric there can be multiple values with different sets of labels.
 ///
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(rename = "camelCase")]
+#[serde(rename_all = "camelCase")]
 pub struct ExternalMetricValue<M> {
+    /// not part of the wire format, items of `ExternalMetricValueList` only carry `TypeMeta`
+    ///
+    #[serde(default, skip_serializing_if = "is_default")]
     pub metadata: metav1::ObjectMeta,
 
     /// the name of the metric
@@ -19,6 +24,7 @@ pub struct ExternalMetricValue<M> {
 
     /// a set of labels that identify a single time series for the metric
     ///
+    #[serde(default, deserialize_with = "null_as_default")]
     pub metric_labels: BTreeMap<String, String>, // `json:"metricLabels" protobuf:"bytes,2,rep,name=metricLabels"`
 
     /// indicates the time at which the metrics were produced
@@ -30,23 +36,129 @@ pub struct ExternalMetricValue<M> {
     /// metrics calculated from cumulative metrics (or zero for
     /// non-calculated instantaneous metrics).
     ///
-    #[serde(default, rename = "window")]
+    #[serde(default, rename = "window", skip_serializing_if = "is_default")]
     pub window_seconds: i64, // `json:"window,omitempty" protobuf:"bytes,4,opt,name=window"`
 
     /// the value of the metric
     ///
     pub value: resource::Quantity, // `json:"value" protobuf:"bytes,5,name=value"`
 
-    phantom: PhantomData<M>,
+    #[serde(skip)]
+    pub phantom: PhantomData<M>,
 }
 
 impl<M: ExternalMetric> k8s::Resource for ExternalMetricValue<M> {
-    const API_VERSION: &'static str = "external.metrics.k8s.io/v1beta1";
-    const GROUP: &'static str = "external.metrics.k8s.io";
+    const API_VERSION: &'static str = concat!(
+        EXTERNAL_METRICS_API_GROUP,
+        "/",
+        EXTERNAL_METRICS_API_VERSION
+    );
+    const GROUP: &'static str = EXTERNAL_METRICS_API_GROUP;
     const KIND: &'static str = M::KIND;
-    const VERSION: &'static str = "v1beta1";
+    const VERSION: &'static str = EXTERNAL_METRICS_API_VERSION;
     const URL_PATH_SEGMENT: &'static str = M::URL_PATH_SEGMENT;
-    type Scope = k8s::ClusterResourceScope;
+    type Scope = k8s::NamespaceResourceScope;
+}
+
+impl<M: ExternalMetric> k8s::Metadata for ExternalMetricValue<M> {
+    type Ty = metav1::ObjectMeta;
+
+    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut <Self as k8s_openapi::Metadata>::Ty {
+        &mut self.metadata
+    }
+}
+
+impl<M> ExternalMetricValue<M> {
+    /// Create new `ExternalMetricValue` of metric `name`
+    ///
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            metadata: default(),
+            metric_name: name.to_string(),
+            metric_labels: default(),
+            timestamp: metav1::Time(Timestamp::default()),
+            window_seconds: default(),
+            value: default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create new `ExternalMetricValue` of metric `name` identified by `labels`
+    ///
+    pub fn with_labels<K, V>(name: impl ToString, labels: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: ToString,
+        V: ToString,
+    {
+        Self::new(name).labels(labels)
+    }
+
+    /// Add label identifying the metric series
+    ///
+    pub fn label(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.metric_labels
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Add labels identifying the metric series
+    ///
+    pub fn labels<K, V>(mut self, labels: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: ToString,
+        V: ToString,
+    {
+        let labels = labels
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+        self.metric_labels.extend(labels);
+        self
+    }
+
+    /// Set timestamp for this `ExternalMetricValue`
+    ///
+    pub fn timestamp(self, timestamp: Timestamp) -> Self {
+        let timestamp = metav1::Time(timestamp);
+        Self { timestamp, ..self }
+    }
+
+    /// Set timestamp of this `ExternalMetricValue` to current time
+    ///
+    pub fn now(self) -> Self {
+        self.timestamp(Timestamp::now())
+    }
+
+    /// Set value of this `ExternalMetricValue`
+    ///
+    pub fn value(self, value: impl IntoQuantity) -> Self {
+        let value = value.into_quantity();
+        Self { value, ..self }
+    }
+
+    /// Set window the value was calculated over, truncated to whole seconds
+    ///
+    pub fn window(self, window: time::Duration) -> Self {
+        let window_seconds = window.as_secs() as i64;
+        Self {
+            window_seconds,
+            ..self
+        }
+    }
+}
+
+impl<M: ExternalMetric> ExternalMetricValue<M> {
+    /// Create new `ExternalMetricValue` of metric `M`
+    ///
+    /// Does not compile for `AnyMetric`, use `ExternalMetricValue::new()` with the metric name.
+    ///
+    pub fn for_metric() -> Self {
+        const { assert!(!M::URL_PATH_SEGMENT.is_empty(), "metric name required") };
+        Self::new(M::URL_PATH_SEGMENT)
+    }
 }
 
 impl<M: ExternalMetric> k8s::ListableResource for ExternalMetricValue<M> {
@@ -54,3 +166,258 @@ impl<M: ExternalMetric> k8s::ListableResource for ExternalMetricValue<M> {
 }
 
 pub type ExternalMetricValueList<M> = k8s::List<ExternalMetricValue<M>>;
+
+/// Request for values of external metric `M` in a namespace
+///
+/// Builds `GET /apis/external.metrics.k8s.io/v1beta1/namespaces/{namespace}/{metric}?labelSelector={selector}`
+/// where `metric` defaults to `M::URL_PATH_SEGMENT`, or is given explicitly for `AnyMetric`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalMetricRequest<M> {
+    namespace: String,
+    metric: String,
+    selector: Option<String>,
+    phantom: PhantomData<M>,
+}
+
+impl<M: ExternalMetric> ExternalMetricRequest<M> {
+    /// Request values of metric `M` in `namespace`
+    ///
+    /// Does not compile for `AnyMetric`, use `ExternalMetricRequest::named()` instead.
+    ///
+    pub fn new(namespace: impl ToString) -> Self {
+        const { assert!(!M::URL_PATH_SEGMENT.is_empty(), "metric name required") };
+        Self::with_metric(namespace, M::URL_PATH_SEGMENT)
+    }
+
+    /// Set metric name, for metrics whose names are only known at runtime
+    ///
+    pub fn metric(self, metric: impl ToString) -> Self {
+        let metric = metric.to_string();
+        Self { metric, ..self }
+    }
+
+    /// Set label selector narrowing down the metric series, e.g. `queue=orders`
+    ///
+    pub fn selector(self, selector: impl ToString) -> Self {
+        let selector = Some(selector.to_string());
+        Self { selector, ..self }
+    }
+
+    /// Path and query of this request
+    ///
+    pub fn uri(&self) -> String {
+        let path = format!(
+            "/apis/{}/namespaces/{}/{}",
+            <ExternalMetricValue<M> as k8s::Resource>::API_VERSION,
+            request::segment(&self.namespace),
+            request::segment(&self.metric)
+        );
+        request::uri(&path, &self.query())
+    }
+
+    /// Build the `http::Request`
+    ///
+    pub fn request(&self) -> Result<http::Request<Vec<u8>>, http::Error> {
+        request::get(&self.uri())
+    }
+
+    /// Parse response to this request
+    ///
+    pub fn parse_response(
+        &self,
+        status: http::StatusCode,
+        body: &[u8],
+    ) -> Result<ExternalMetricValueList<M>, ResponseError> {
+        request::parse(status, body)
+    }
+
+    fn with_metric(namespace: impl ToString, metric: impl ToString) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            metric: metric.to_string(),
+            selector: None,
+            phantom: PhantomData,
+        }
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        self.selector
+            .as_deref()
+            .map(|selector| ("labelSelector", selector))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl ExternalMetricRequest<AnyMetric> {
+    /// Request values of `metric` in `namespace`, for metrics whose names are only known at runtime
+    ///
+    pub fn named(namespace: impl ToString, metric: impl ToString) -> Self {
+        Self::with_metric(namespace, metric)
+    }
+}
+
+/// Go serializes nil maps as `null`
+///
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s::serde_json as json;
+
+    #[derive(Debug)]
+    struct Queue;
+
+    impl ExternalMetric for Queue {
+        const KIND: &'static str = "ExternalMetricValue";
+        const URL_PATH_SEGMENT: &'static str = "rabbitmq_queue_messages_ready";
+    }
+
+    const GOLDEN: &str = include_str!("../../testdata/external-metrics-v1beta1.json");
+    const GOLDEN_KEDA: &str = include_str!("../../testdata/external-metrics-v1beta1-keda.json");
+
+    #[test]
+    fn golden_deserialize() {
+        let list = json::from_str::<ExternalMetricValueList<Queue>>(GOLDEN).unwrap();
+        assert_eq!(list.items.len(), 2);
+
+        let first = &list.items[0];
+        assert_eq!(first.metric_name, "rabbitmq_queue_messages_ready");
+        assert_eq!(first.metric_labels["queue"], "orders");
+        assert_eq!(first.metric_labels["vhost"], "/");
+        assert_eq!(first.window_seconds, 30);
+        assert_eq!(first.value, resource::Quantity("42".to_string()));
+        assert_eq!(
+            first.timestamp.0,
+            "2024-03-18T09:25:10Z".parse::<Timestamp>().unwrap()
+        );
+
+        assert_eq!(list.items[1].window_seconds, 0);
+    }
+
+    #[test]
+    fn golden_roundtrip() {
+        let list = json::from_str::<ExternalMetricValueList<Queue>>(GOLDEN).unwrap();
+        let golden = json::from_str::<json::Value>(GOLDEN).unwrap();
+        assert_eq!(json::to_value(&list).unwrap(), golden);
+    }
+
+    #[test]
+    fn golden_null_labels() {
+        let list = json::from_str::<ExternalMetricValueList<Queue>>(GOLDEN_KEDA).unwrap();
+        let value = &list.items[0];
+        assert_eq!(value.metric_name, "s0-rabbitmq-orders");
+        assert!(value.metric_labels.is_empty());
+        assert_eq!(value.value, resource::Quantity("12".to_string()));
+    }
+
+    fn namespace_scoped<K: k8s::Resource<Scope = k8s::NamespaceResourceScope>>() {}
+
+    #[test]
+    fn namespaced() {
+        namespace_scoped::<ExternalMetricValue<Queue>>();
+        assert_eq!(
+            ExternalMetricRequest::<Queue>::new("messaging").uri(),
+            "/apis/external.metrics.k8s.io/v1beta1/namespaces/messaging/rabbitmq_queue_messages_ready"
+        );
+        let value = json::from_str::<ExternalMetricValueList<Queue>>(GOLDEN)
+            .unwrap()
+            .items
+            .remove(0);
+        assert!(k8s::Metadata::metadata(&value).namespace.is_none());
+    }
+
+    #[test]
+    fn request() {
+        let request = ExternalMetricRequest::<Queue>::new("messaging")
+            .selector("queue=orders")
+            .request()
+            .unwrap();
+        assert_eq!(request.method(), http::Method::GET);
+        assert_eq!(
+            request.uri(),
+            "/apis/external.metrics.k8s.io/v1beta1/namespaces/messaging/rabbitmq_queue_messages_ready?labelSelector=queue%3Dorders"
+        );
+    }
+
+    #[test]
+    fn request_escaped() {
+        let request = ExternalMetricRequest::<Queue>::new("default")
+            .metric("orders/ready?#")
+            .selector("queue=a/b");
+        assert_eq!(
+            request.uri(),
+            "/apis/external.metrics.k8s.io/v1beta1/namespaces/default/orders%2Fready%3F%23?labelSelector=queue%3Da%2Fb"
+        );
+    }
+
+    #[test]
+    fn request_dynamic_metric() {
+        let request = ExternalMetricRequest::<Queue>::new("default").metric("s0-rabbitmq-orders");
+        assert_eq!(
+            request.uri(),
+            "/apis/external.metrics.k8s.io/v1beta1/namespaces/default/s0-rabbitmq-orders"
+        );
+
+        let list = request
+            .parse_response(http::StatusCode::OK, GOLDEN_KEDA.as_bytes())
+            .unwrap();
+        assert_eq!(list.items[0].metric_name, "s0-rabbitmq-orders");
+
+        let err = request
+            .parse_response(http::StatusCode::NOT_FOUND, b"404 page not found")
+            .unwrap_err();
+        assert_eq!(err.code(), Some(404));
+    }
+
+    #[test]
+    fn request_any_metric() {
+        let request = ExternalMetricRequest::<AnyMetric>::named("default", "s0-rabbitmq-orders");
+        assert_eq!(
+            request.uri(),
+            "/apis/external.metrics.k8s.io/v1beta1/namespaces/default/s0-rabbitmq-orders"
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let timestamp = "2024-03-18T09:25:10Z".parse::<Timestamp>().unwrap();
+        let value = ExternalMetricValue::<Queue>::for_metric()
+            .labels([("queue", "orders")])
+            .label("vhost", "/")
+            .value(42)
+            .window(time::Duration::from_secs(30))
+            .timestamp(timestamp);
+        let invoices = ExternalMetricValue::<Queue>::with_labels(
+            Queue::URL_PATH_SEGMENT,
+            [("queue", "invoices"), ("vhost", "/")],
+        )
+        .value(0.0)
+        .timestamp(timestamp);
+
+        let list = ExternalMetricValueList {
+            items: vec![value, invoices],
+            metadata: default(),
+        };
+        let golden = json::from_str::<json::Value>(GOLDEN).unwrap();
+        assert_eq!(json::to_value(&list).unwrap(), golden);
+    }
+
+    #[test]
+    fn now() {
+        let value = ExternalMetricValue::<Queue>::new("s0-rabbitmq-orders")
+            .value(12)
+            .now();
+        assert_eq!(value.metric_name, "s0-rabbitmq-orders");
+        assert!(value.metric_labels.is_empty());
+        assert_ne!(value.timestamp.0, Timestamp::default());
+    }
+}
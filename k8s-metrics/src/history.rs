@@ -0,0 +1,603 @@
+//! Bounded in-memory history of resource metrics
+//!
+//! Metrics API only serves the latest usage. `History` keeps the last few snapshots of
+//! every node, pod and container so that usage can be summarized over a trailing window.
+//!
+use std::collections::{BTreeMap, VecDeque};
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+use super::*;
+
+/// Object a series of samples belongs to
+///
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SeriesKey {
+    Node {
+        name: String,
+    },
+    /// Total usage of all containers of a pod
+    ///
+    Pod {
+        namespace: String,
+        name: String,
+    },
+    Container {
+        namespace: String,
+        pod: String,
+        name: String,
+    },
+}
+
+impl SeriesKey {
+    pub fn node(name: &str) -> Self {
+        let name = name.to_string();
+        Self::Node { name }
+    }
+
+    pub fn pod(namespace: &str, name: &str) -> Self {
+        let namespace = namespace.to_string();
+        let name = name.to_string();
+        Self::Pod { namespace, name }
+    }
+
+    pub fn container(namespace: &str, pod: &str, name: &str) -> Self {
+        let namespace = namespace.to_string();
+        let pod = pod.to_string();
+        let name = name.to_string();
+        Self::Container {
+            namespace,
+            pod,
+            name,
+        }
+    }
+}
+
+/// Usage averaged over `window` ending at `timestamp`
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub timestamp: Timestamp,
+    pub window: time::Duration,
+    pub cpu: Cpu,
+    pub memory: Memory,
+}
+
+impl Sample {
+    /// Start of the window the sample covers
+    ///
+    pub fn start(&self) -> Timestamp {
+        self.timestamp
+            .checked_sub(self.window)
+            .unwrap_or(Timestamp::MIN)
+    }
+
+    fn reading(&self) -> Reading {
+        Reading {
+            cpu: self.cpu,
+            memory: self.memory,
+        }
+    }
+}
+
+/// CPU and memory usage summarizing several samples
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reading {
+    pub cpu: Cpu,
+    pub memory: Memory,
+}
+
+impl Reading {
+    fn from_f64(nanocores: f64, bytes: f64) -> Self {
+        Self {
+            cpu: Cpu::from_nanocores(nanocores.round() as i64),
+            memory: Memory::from_bytes(bytes.round() as i64),
+        }
+    }
+}
+
+/// Latest samples of a single object, oldest first
+///
+#[derive(Clone, Debug)]
+pub struct Series {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl Series {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let samples = VecDeque::with_capacity(capacity);
+        Self { capacity, samples }
+    }
+
+    /// Append `sample`, evicting the oldest one when full
+    ///
+    /// Samples not newer than the latest one are ignored, metrics-server keeps serving
+    /// the same sample until it scrapes the node again.
+    ///
+    fn push(&mut self, sample: Sample) {
+        if self
+            .latest()
+            .is_some_and(|latest| latest.timestamp >= sample.timestamp)
+        {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    /// Samples taken between `start` and `end`, both inclusive
+    ///
+    pub fn range(&self, start: Timestamp, end: Timestamp) -> Window<'_> {
+        let samples = self
+            .samples
+            .iter()
+            .filter(|sample| start <= sample.timestamp && sample.timestamp <= end)
+            .collect();
+        Window {
+            start,
+            end,
+            samples,
+        }
+    }
+
+    /// Samples taken during `window` ending at the latest sample
+    ///
+    pub fn trailing(&self, window: time::Duration) -> Window<'_> {
+        let end = self
+            .latest()
+            .map_or(Timestamp::UNIX_EPOCH, |latest| latest.timestamp);
+        let start = end.checked_sub(window).unwrap_or(Timestamp::MIN);
+        self.range(start, end)
+    }
+}
+
+/// Samples of a series within a time range
+///
+#[derive(Clone, Debug)]
+pub struct Window<'a> {
+    start: Timestamp,
+    end: Timestamp,
+    samples: Vec<&'a Sample>,
+}
+
+impl Window<'_> {
+    pub fn start(&self) -> Timestamp {
+        self.start
+    }
+
+    pub fn end(&self) -> Timestamp {
+        self.end
+    }
+
+    pub fn samples(&self) -> &[&Sample] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Lowest CPU and lowest memory usage, not necessarily of the same sample
+    ///
+    pub fn min(&self) -> Option<Reading> {
+        Some(Reading {
+            cpu: self.samples.iter().map(|sample| sample.cpu).min()?,
+            memory: self.samples.iter().map(|sample| sample.memory).min()?,
+        })
+    }
+
+    /// Highest CPU and highest memory usage, not necessarily of the same sample
+    ///
+    pub fn max(&self) -> Option<Reading> {
+        Some(Reading {
+            cpu: self.samples.iter().map(|sample| sample.cpu).max()?,
+            memory: self.samples.iter().map(|sample| sample.memory).max()?,
+        })
+    }
+
+    /// Average usage, weighted by how much of each sample window falls within this window
+    ///
+    /// Samples with an empty window are weighted equally if there are no others.
+    ///
+    pub fn avg(&self) -> Option<Reading> {
+        let weights = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let start = sample.start().max(self.start);
+                let overlap = sample.timestamp.duration_since(start).as_nanos();
+                overlap.max(0) as f64
+            })
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        let (weights, total) = if total > 0.0 {
+            (weights, total)
+        } else {
+            (vec![1.0; self.samples.len()], self.samples.len() as f64)
+        };
+        if total == 0.0 {
+            return None;
+        }
+
+        let (nanocores, bytes) = self.samples.iter().zip(weights).fold(
+            (0.0, 0.0),
+            |(nanocores, bytes), (sample, weight)| {
+                (
+                    nanocores + sample.cpu.nanocores() as f64 * weight,
+                    bytes + sample.memory.bytes() as f64 * weight,
+                )
+            },
+        );
+        Some(Reading::from_f64(nanocores / total, bytes / total))
+    }
+
+    /// Nearest-rank `percentile` of CPU and of memory usage, `percentile` being within `0..=100`
+    ///
+    pub fn percentile(&self, percentile: f64) -> Option<Reading> {
+        let mut cpu = self
+            .samples
+            .iter()
+            .map(|sample| sample.cpu)
+            .collect::<Vec<_>>();
+        let mut memory = self
+            .samples
+            .iter()
+            .map(|sample| sample.memory)
+            .collect::<Vec<_>>();
+        cpu.sort_unstable();
+        memory.sort_unstable();
+
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * cpu.len() as f64).ceil() as usize;
+        let index = rank.saturating_sub(1);
+        Some(Reading {
+            cpu: *cpu.get(index)?,
+            memory: *memory.get(index)?,
+        })
+    }
+
+    /// Exponentially weighted moving average, oldest sample first
+    ///
+    /// Each sample is weighted by its own window, a sample covering `half_life`
+    /// contributes half of the average.
+    ///
+    pub fn ewma(&self, half_life: time::Duration) -> Option<Reading> {
+        let (first, rest) = self.samples.split_first()?;
+        let initial = (first.cpu.nanocores() as f64, first.memory.bytes() as f64);
+        let (nanocores, bytes) = rest.iter().fold(initial, |(nanocores, bytes), sample| {
+            let alpha = if half_life.is_zero() {
+                1.0
+            } else {
+                1.0 - 0.5_f64.powf(sample.window.as_secs_f64() / half_life.as_secs_f64())
+            };
+            (
+                nanocores + alpha * (sample.cpu.nanocores() as f64 - nanocores),
+                bytes + alpha * (sample.memory.bytes() as f64 - bytes),
+            )
+        });
+        Some(Reading::from_f64(nanocores, bytes))
+    }
+
+    /// Latest sample, the one closest to the end of this window
+    ///
+    pub fn last(&self) -> Option<Reading> {
+        self.samples.last().map(|sample| sample.reading())
+    }
+}
+
+/// Bounded history of node, pod and container metrics
+///
+#[derive(Clone, Debug)]
+pub struct History {
+    capacity: usize,
+    series: BTreeMap<SeriesKey, Series>,
+}
+
+impl History {
+    /// History keeping up to `capacity` samples of every node, pod and container
+    ///
+    pub fn new(capacity: usize) -> Self {
+        let series = default();
+        Self { capacity, series }
+    }
+
+    /// Record metrics of `pod` and each of its containers
+    ///
+    /// Nothing is recorded if any usage fails to parse.
+    ///
+    pub fn ingest_pod(&mut self, pod: &PodMetrics) -> Result<(), QuantityParseError> {
+        let namespace = pod.metadata.namespace.as_deref().unwrap_or_default();
+        let name = pod.metadata.name.as_deref().unwrap_or_default();
+        let sample = |cpu, memory| Sample {
+            timestamp: pod.timestamp.0,
+            window: pod.window,
+            cpu,
+            memory,
+        };
+
+        let containers = pod
+            .containers
+            .iter()
+            .map(|container| {
+                let key = SeriesKey::container(namespace, name, &container.name);
+                Ok((key, sample(container.cpu()?, container.memory()?)))
+            })
+            .collect::<Result<Vec<_>, QuantityParseError>>()?;
+        let total = sample(pod.cpu()?, pod.memory()?);
+
+        self.push(SeriesKey::pod(namespace, name), total);
+        for (key, sample) in containers {
+            self.push(key, sample);
+        }
+        Ok(())
+    }
+
+    /// Record metrics of `node`
+    ///
+    pub fn ingest_node(&mut self, node: &NodeMetrics) -> Result<(), QuantityParseError> {
+        let name = node.metadata.name.as_deref().unwrap_or_default();
+        let sample = Sample {
+            timestamp: node.timestamp.0,
+            window: node.window,
+            cpu: node.cpu()?,
+            memory: node.memory()?,
+        };
+        self.push(SeriesKey::node(name), sample);
+        Ok(())
+    }
+
+    fn push(&mut self, key: SeriesKey, sample: Sample) {
+        self.series
+            .entry(key)
+            .or_insert_with(|| Series::new(self.capacity))
+            .push(sample);
+    }
+
+    pub fn series(&self, key: &SeriesKey) -> Option<&Series> {
+        self.series.get(key)
+    }
+
+    pub fn node(&self, name: &str) -> Option<&Series> {
+        self.series(&SeriesKey::node(name))
+    }
+
+    pub fn pod(&self, namespace: &str, name: &str) -> Option<&Series> {
+        self.series(&SeriesKey::pod(namespace, name))
+    }
+
+    pub fn container(&self, namespace: &str, pod: &str, name: &str) -> Option<&Series> {
+        self.series(&SeriesKey::container(namespace, pod, name))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &SeriesKey> {
+        self.series.keys()
+    }
+
+    /// Forget node `name`
+    ///
+    pub fn remove_node(&mut self, name: &str) {
+        self.series.remove(&SeriesKey::node(name));
+    }
+
+    /// Forget pod `name` in `namespace` and all its containers
+    ///
+    pub fn remove_pod(&mut self, namespace: &str, name: &str) {
+        self.series.retain(|key, _| match key {
+            SeriesKey::Pod {
+                namespace: ns,
+                name: pod,
+            }
+            | SeriesKey::Container {
+                namespace: ns, pod, ..
+            } => ns != namespace || pod != name,
+            SeriesKey::Node { .. } => true,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use v1beta1::{Container, Usage};
+
+    use super::*;
+
+    fn timestamp(second: i64) -> metav1::Time {
+        metav1::Time(Timestamp::from_second(1665316280 + second).unwrap())
+    }
+
+    fn pod(second: i64, nginx_millicores: i64, sidecar_mib: i64) -> PodMetrics {
+        let container = |name: &str, cpu: Cpu, memory: Memory| Container {
+            name: name.to_string(),
+            usage: Usage {
+                cpu: cpu.into(),
+                memory: memory.into(),
+            },
+        };
+        PodMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some("web-0".to_string()),
+                namespace: Some("default".to_string()),
+                ..default()
+            },
+            containers: vec![
+                container(
+                    "nginx",
+                    Cpu::from_millicores(nginx_millicores),
+                    Memory::from_mib(64),
+                ),
+                container(
+                    "sidecar",
+                    Cpu::from_millicores(10),
+                    Memory::from_mib(sidecar_mib),
+                ),
+            ],
+            timestamp: timestamp(second),
+            window: time::Duration::from_secs(10),
+        }
+    }
+
+    fn node(second: i64, window: u64, millicores: i64) -> NodeMetrics {
+        NodeMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some("node-1".to_string()),
+                ..default()
+            },
+            timestamp: timestamp(second),
+            window: time::Duration::from_secs(window),
+            usage: Usage {
+                cpu: Cpu::from_millicores(millicores).into(),
+                memory: Memory::from_gib(1).into(),
+            },
+        }
+    }
+
+    fn history() -> History {
+        let mut history = History::new(4);
+        let samples = [
+            (0, 100, 16),
+            (10, 300, 32),
+            (20, 200, 8),
+            (30, 400, 64),
+            (40, 500, 24),
+        ];
+        for (second, millicores, mib) in samples {
+            history.ingest_pod(&pod(second, millicores, mib)).unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let history = history();
+        let nginx = history.container("default", "web-0", "nginx").unwrap();
+        assert_eq!(nginx.len(), 4);
+        let seconds = nginx
+            .samples()
+            .map(|sample| sample.timestamp.as_second() - 1665316280)
+            .collect::<Vec<_>>();
+        assert_eq!(seconds, [10, 20, 30, 40]);
+
+        let pod = history.pod("default", "web-0").unwrap();
+        assert_eq!(pod.latest().unwrap().cpu, Cpu::from_millicores(510));
+        assert_eq!(history.keys().count(), 3);
+    }
+
+    #[test]
+    fn stale_samples() {
+        let mut history = history();
+        history.ingest_pod(&pod(40, 900, 1)).unwrap();
+        history.ingest_pod(&pod(35, 900, 1)).unwrap();
+        let pod = history.pod("default", "web-0").unwrap();
+        assert_eq!(pod.len(), 4);
+        assert_eq!(pod.latest().unwrap().cpu, Cpu::from_millicores(510));
+    }
+
+    #[test]
+    fn stats() {
+        let history = history();
+        let nginx = history.container("default", "web-0", "nginx").unwrap();
+        let sidecar = history.container("default", "web-0", "sidecar").unwrap();
+
+        let window = nginx.trailing(time::Duration::from_secs(20));
+        assert_eq!(window.len(), 3);
+        assert_eq!(window.min().unwrap().cpu, Cpu::from_millicores(200));
+        assert_eq!(window.max().unwrap().cpu, Cpu::from_millicores(500));
+        assert_eq!(window.last().unwrap().cpu, Cpu::from_millicores(500));
+
+        // The window of the earliest sample ends right at the start
+        assert_eq!(window.avg().unwrap().cpu, Cpu::from_millicores(450));
+
+        let window = sidecar.trailing(time::Duration::from_secs(30));
+        assert_eq!(window.min().unwrap().memory, Memory::from_mib(8));
+        assert_eq!(window.max().unwrap().memory, Memory::from_mib(64));
+        assert_eq!(
+            window.percentile(50.0).unwrap().memory,
+            Memory::from_mib(24)
+        );
+        assert_eq!(
+            window.percentile(75.0).unwrap().memory,
+            Memory::from_mib(32)
+        );
+        assert_eq!(
+            window.percentile(100.0).unwrap().memory,
+            Memory::from_mib(64)
+        );
+        assert_eq!(window.percentile(0.0).unwrap().memory, Memory::from_mib(8));
+    }
+
+    #[test]
+    fn ewma() {
+        let history = history();
+        let nginx = history.container("default", "web-0", "nginx").unwrap();
+        let window = nginx.trailing(time::Duration::from_secs(60));
+
+        // Each 10s sample covers one half-life: 300 -> 250 -> 325 -> 412.5
+        let ewma = window.ewma(time::Duration::from_secs(10)).unwrap();
+        assert_eq!(ewma.cpu, Cpu::from_cores(0.4125));
+        assert_eq!(
+            window.ewma(time::Duration::ZERO).unwrap(),
+            window.last().unwrap()
+        );
+    }
+
+    #[test]
+    fn sample_windows() {
+        let mut history = History::new(10);
+        history.ingest_node(&node(10, 30, 1000)).unwrap();
+        history.ingest_node(&node(20, 10, 2000)).unwrap();
+        let series = history.node("node-1").unwrap();
+
+        // Only the last 5 seconds of the first sample window fall within the range
+        let window = series.range(timestamp(5).0, timestamp(20).0);
+        assert_eq!(window.len(), 2);
+        assert_eq!(
+            window.avg().unwrap().cpu,
+            Cpu::from_nanocores(1_666_666_667)
+        );
+
+        let window = series.trailing(time::Duration::ZERO);
+        assert_eq!(window.len(), 1);
+        assert_eq!(window.avg().unwrap().cpu, Cpu::from_millicores(2000));
+
+        let window = series.range(timestamp(30).0, timestamp(40).0);
+        assert!(window.is_empty());
+        assert_eq!(window.avg(), None);
+        assert_eq!(window.percentile(50.0), None);
+        assert_eq!(window.ewma(time::Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut history = history();
+        history.ingest_node(&node(0, 10, 1000)).unwrap();
+        history.remove_pod("default", "web-0");
+        let keys = history.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys, [SeriesKey::node("node-1")]);
+        history.remove_node("node-1");
+        assert_eq!(history.keys().count(), 0);
+    }
+}
@@ -0,0 +1,34 @@
+//! Resource metrics collected directly from kubelet, without metrics-server
+//!
+use super::*;
+
+pub use resource_metrics::{PodKey, ResourceSample, ResourceScrape};
+
+pub mod cadvisor;
+mod prometheus;
+mod resource_metrics;
+#[cfg(feature = "scraper")]
+pub mod scraper;
+pub mod summary;
+
+/// Malformed kubelet response
+///
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid metrics at line {line}: {text}")]
+pub struct ParseError {
+    line: usize,
+    text: String,
+}
+
+impl ParseError {
+    fn new(line: usize, text: &str) -> Self {
+        let text = text.to_string();
+        Self { line, text }
+    }
+
+    /// Line number of the malformed line, starting at 1
+    ///
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
@@ -0,0 +1,462 @@
+//! Container statistics from kubelet `/metrics/cadvisor` endpoint
+//!
+use std::collections::BTreeMap;
+
+use v1beta1::{Container, PodMetrics};
+
+use super::*;
+
+/// Single scrape of kubelet `/metrics/cadvisor` endpoint
+///
+/// Only series of pod containers are kept. Each pod also has an entry for its
+/// cgroup as a whole, with empty container name (`POD` for older runtimes),
+/// which is the only one with network statistics.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CadvisorScrape {
+    /// Statistics of containers, by pod and container name
+    ///
+    pub pods: BTreeMap<PodKey, BTreeMap<String, ContainerStats>>,
+}
+
+impl CadvisorScrape {
+    /// Parse kubelet response `text`, scraped at `scraped_at`
+    ///
+    /// `scraped_at` is used for samples without explicit timestamp.
+    ///
+    pub fn parse(text: &str, scraped_at: Timestamp) -> Result<Self, ParseError> {
+        let mut pods = BTreeMap::<PodKey, BTreeMap<String, ContainerStats>>::new();
+
+        for sample in prometheus::parse(text) {
+            let sample = sample?;
+            if !sample.name.starts_with("container_") {
+                continue;
+            }
+            // Older cAdvisor versions also report usage of each CPU
+            if sample.label("cpu").is_some_and(|cpu| cpu != "total") {
+                continue;
+            }
+            let (Some(namespace), Some(name), Some(container)) = (
+                sample
+                    .label("namespace")
+                    .filter(|namespace| !namespace.is_empty()),
+                sample.label("pod").filter(|pod| !pod.is_empty()),
+                sample.label("container"),
+            ) else {
+                continue;
+            };
+            let timestamp = match sample.timestamp {
+                Some(millis) => Timestamp::from_millisecond(millis).map_err(|_| sample.error())?,
+                None => scraped_at,
+            };
+
+            let key = PodKey {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            };
+            let stats = pods
+                .entry(key)
+                .or_default()
+                .entry(container.to_string())
+                .or_default();
+            if stats.timestamp.is_none_or(|latest| latest < timestamp) {
+                stats.timestamp = Some(timestamp);
+            }
+            stats.update(&sample)?;
+        }
+
+        Ok(Self { pods })
+    }
+
+    /// Statistics of `container` of pod `name` in `namespace`
+    ///
+    pub fn container(
+        &self,
+        namespace: &str,
+        name: &str,
+        container: &str,
+    ) -> Option<&ContainerStats> {
+        self.containers(namespace, name)?.get(container)
+    }
+
+    /// Statistics of the cgroup of pod `name` in `namespace` as a whole
+    ///
+    pub fn pod(&self, namespace: &str, name: &str) -> Option<&ContainerStats> {
+        let containers = self.containers(namespace, name)?;
+        containers.get("").or_else(|| containers.get("POD"))
+    }
+
+    /// Containers of `pod` along with their statistics, if any
+    ///
+    pub fn join<'a>(
+        &'a self,
+        pod: &'a PodMetrics,
+    ) -> impl Iterator<Item = (&'a Container, Option<&'a ContainerStats>)> {
+        let containers = pod
+            .metadata
+            .namespace
+            .as_deref()
+            .zip(pod.metadata.name.as_deref());
+        let containers = containers.and_then(|(namespace, name)| self.containers(namespace, name));
+        pod.containers.iter().map(move |container| {
+            (
+                container,
+                containers.and_then(|stats| stats.get(&container.name)),
+            )
+        })
+    }
+
+    fn containers(&self, namespace: &str, name: &str) -> Option<&BTreeMap<String, ContainerStats>> {
+        let key = PodKey {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        };
+        self.pods.get(&key)
+    }
+}
+
+/// Statistics of a container as reported by cAdvisor
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContainerStats {
+    /// Time of the latest sample
+    ///
+    pub timestamp: Option<Timestamp>,
+    pub start_time: Option<Timestamp>,
+    pub cpu: CpuStats,
+    pub memory: MemoryStats,
+    /// Network usage by interface name
+    ///
+    pub network: BTreeMap<String, NetworkStats>,
+    /// Filesystem usage by device name
+    ///
+    pub filesystems: BTreeMap<String, FsStats>,
+}
+
+impl ContainerStats {
+    /// Share of CFS periods in which the container was throttled since it started
+    ///
+    pub fn throttled_ratio(&self) -> Option<f64> {
+        ratio(self.cpu.cfs_throttled_periods?, self.cpu.cfs_periods?)
+    }
+
+    /// Share of CFS periods in which the container was throttled since `previous` scrape
+    ///
+    /// A high ratio means CPU usage is capped by the container CPU limit rather than
+    /// low because the container has nothing to do.
+    ///
+    pub fn throttled_ratio_since(&self, previous: &Self) -> Option<f64> {
+        let throttled = self
+            .cpu
+            .cfs_throttled_periods?
+            .checked_sub(previous.cpu.cfs_throttled_periods?)?;
+        let periods = self
+            .cpu
+            .cfs_periods?
+            .checked_sub(previous.cpu.cfs_periods?)?;
+        ratio(throttled, periods)
+    }
+
+    /// Time the container spent throttled since `previous` scrape
+    ///
+    pub fn throttled_since(&self, previous: &Self) -> Option<time::Duration> {
+        let seconds = self.cpu.cfs_throttled_seconds? - previous.cpu.cfs_throttled_seconds?;
+        time::Duration::try_from_secs_f64(seconds).ok()
+    }
+
+    fn update(&mut self, sample: &prometheus::Sample<'_>) -> Result<(), ParseError> {
+        let value = sample.value;
+        let count = value as u64;
+        let cpu = &mut self.cpu;
+        let memory = &mut self.memory;
+        match sample.name {
+            "container_cpu_usage_seconds_total" => cpu.usage_seconds = Some(value),
+            "container_cpu_user_seconds_total" => cpu.user_seconds = Some(value),
+            "container_cpu_system_seconds_total" => cpu.system_seconds = Some(value),
+            "container_cpu_cfs_periods_total" => cpu.cfs_periods = Some(count),
+            "container_cpu_cfs_throttled_periods_total" => cpu.cfs_throttled_periods = Some(count),
+            "container_cpu_cfs_throttled_seconds_total" => cpu.cfs_throttled_seconds = Some(value),
+            "container_spec_cpu_quota" => cpu.quota_micros = Some(count),
+            "container_spec_cpu_period" => cpu.period_micros = Some(count),
+            "container_spec_cpu_shares" => cpu.shares = Some(count),
+            "container_memory_usage_bytes" => memory.usage_bytes = Some(count),
+            "container_memory_working_set_bytes" => memory.working_set_bytes = Some(count),
+            "container_memory_rss" => memory.rss_bytes = Some(count),
+            "container_memory_cache" => memory.cache_bytes = Some(count),
+            "container_memory_swap" => memory.swap_bytes = Some(count),
+            "container_memory_failcnt" => memory.failcnt = Some(count),
+            "container_oom_events_total" => memory.oom_events = Some(count),
+            "container_spec_memory_limit_bytes" => memory.limit_bytes = Some(count),
+            "container_start_time_seconds" => {
+                let millis = (value * 1000.0).round() as i64;
+                let start_time = Timestamp::from_millisecond(millis).map_err(|_| sample.error())?;
+                self.start_time = Some(start_time);
+            }
+            "container_network_receive_bytes_total" => self.network(sample)?.rx_bytes = Some(count),
+            "container_network_receive_errors_total" => {
+                self.network(sample)?.rx_errors = Some(count);
+            }
+            "container_network_receive_packets_dropped_total" => {
+                self.network(sample)?.rx_dropped = Some(count);
+            }
+            "container_network_transmit_bytes_total" => {
+                self.network(sample)?.tx_bytes = Some(count);
+            }
+            "container_network_transmit_errors_total" => {
+                self.network(sample)?.tx_errors = Some(count);
+            }
+            "container_network_transmit_packets_dropped_total" => {
+                self.network(sample)?.tx_dropped = Some(count);
+            }
+            "container_fs_reads_bytes_total" => self.filesystem(sample)?.read_bytes = Some(count),
+            "container_fs_writes_bytes_total" => {
+                self.filesystem(sample)?.written_bytes = Some(count);
+            }
+            "container_fs_usage_bytes" => self.filesystem(sample)?.usage_bytes = Some(count),
+            "container_fs_limit_bytes" => self.filesystem(sample)?.limit_bytes = Some(count),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Network stats of the interface labelling `sample`
+    ///
+    fn network(
+        &mut self,
+        sample: &prometheus::Sample<'_>,
+    ) -> Result<&mut NetworkStats, ParseError> {
+        let interface = sample.label("interface").ok_or_else(|| sample.error())?;
+        Ok(self.network.entry(interface.to_string()).or_default())
+    }
+
+    /// Filesystem stats of the device labelling `sample`
+    ///
+    fn filesystem(&mut self, sample: &prometheus::Sample<'_>) -> Result<&mut FsStats, ParseError> {
+        let device = sample.label("device").ok_or_else(|| sample.error())?;
+        Ok(self.filesystems.entry(device.to_string()).or_default())
+    }
+}
+
+fn ratio(part: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| part as f64 / total as f64)
+}
+
+/// CPU usage and CFS bandwidth control, counters are cumulative
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CpuStats {
+    pub usage_seconds: Option<f64>,
+    pub user_seconds: Option<f64>,
+    pub system_seconds: Option<f64>,
+    /// Elapsed CFS enforcement periods
+    ///
+    pub cfs_periods: Option<u64>,
+    /// CFS enforcement periods in which the container was throttled
+    ///
+    pub cfs_throttled_periods: Option<u64>,
+    pub cfs_throttled_seconds: Option<f64>,
+    /// CFS quota, in microseconds per period
+    ///
+    pub quota_micros: Option<u64>,
+    /// CFS period, in microseconds
+    ///
+    pub period_micros: Option<u64>,
+    pub shares: Option<u64>,
+}
+
+impl CpuStats {
+    /// CPU limit of the container, `None` if unlimited
+    ///
+    pub fn limit(&self) -> Option<Cpu> {
+        let quota = self.quota_micros.filter(|quota| *quota > 0)?;
+        let period = self.period_micros.filter(|period| *period > 0)?;
+        Some(Cpu::from_cores(quota as f64 / period as f64))
+    }
+}
+
+/// Memory usage, in bytes
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub usage_bytes: Option<u64>,
+    pub working_set_bytes: Option<u64>,
+    pub rss_bytes: Option<u64>,
+    pub cache_bytes: Option<u64>,
+    pub swap_bytes: Option<u64>,
+    /// Number of times memory usage hit the limit
+    ///
+    pub failcnt: Option<u64>,
+    pub oom_events: Option<u64>,
+    /// Memory limit, zero if unlimited
+    ///
+    pub limit_bytes: Option<u64>,
+}
+
+impl MemoryStats {
+    /// Memory limit of the container, `None` if unlimited
+    ///
+    pub fn limit(&self) -> Option<Memory> {
+        let bytes = self.limit_bytes.filter(|bytes| *bytes > 0)?;
+        Some(Memory::from_bytes(bytes.try_into().ok()?))
+    }
+}
+
+/// Network usage of a single interface, counters are cumulative
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub rx_bytes: Option<u64>,
+    pub rx_errors: Option<u64>,
+    pub rx_dropped: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    pub tx_errors: Option<u64>,
+    pub tx_dropped: Option<u64>,
+}
+
+/// Usage of a single filesystem device, counters are cumulative
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsStats {
+    pub read_bytes: Option<u64>,
+    pub written_bytes: Option<u64>,
+    pub usage_bytes: Option<u64>,
+    pub limit_bytes: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use v1beta1::Usage;
+
+    use super::*;
+
+    const SCRAPE: &str = include_str!("../../testdata/kubelet-metrics-cadvisor.txt");
+
+    fn scrape(text: &str) -> CadvisorScrape {
+        CadvisorScrape::parse(text, Timestamp::UNIX_EPOCH).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let scrape = scrape(SCRAPE);
+
+        // root cgroup has no pod
+        assert_eq!(scrape.pods.len(), 1);
+
+        let nginx = scrape.container("default", "web-0", "nginx").unwrap();
+        assert_eq!(nginx.timestamp.unwrap().as_millisecond(), 1666186373120);
+        assert_eq!(nginx.start_time.unwrap().as_second(), 1666185000);
+        assert_eq!(nginx.cpu.usage_seconds, Some(120.5));
+        assert_eq!(nginx.cpu.user_seconds, Some(100.0));
+        assert_eq!(nginx.cpu.limit(), Some(Cpu::from_millicores(250)));
+        assert_eq!(nginx.memory.rss_bytes, Some(50 << 20));
+        assert_eq!(nginx.memory.cache_bytes, Some(4 << 20));
+        assert_eq!(nginx.memory.limit(), Some(Memory::from_mib(128)));
+        assert_eq!(nginx.filesystems["/dev/vda"].read_bytes, Some(4460544));
+        assert!(nginx.network.is_empty());
+
+        let sidecar = scrape.container("default", "web-0", "sidecar").unwrap();
+        assert_eq!(sidecar.cpu.limit(), None);
+        assert_eq!(sidecar.memory.limit(), None);
+
+        let pod = scrape.pod("default", "web-0").unwrap();
+        assert_eq!(pod.cpu.usage_seconds, Some(123.75));
+        assert_eq!(pod.network["eth0"].rx_bytes, Some(1052338));
+        assert_eq!(pod.network["eth0"].tx_errors, Some(0));
+
+        assert!(scrape.container("default", "web-1", "nginx").is_none());
+    }
+
+    #[test]
+    fn per_cpu() {
+        let scrape = scrape(
+            "container_cpu_usage_seconds_total{container=\"nginx\",cpu=\"cpu00\",namespace=\"default\",pod=\"web-0\"} 1\n\
+             container_cpu_usage_seconds_total{container=\"nginx\",cpu=\"total\",namespace=\"default\",pod=\"web-0\"} 2\n\
+             container_cpu_usage_seconds_total{container=\"nginx\",cpu=\"cpu01\",namespace=\"default\",pod=\"web-0\"} 1\n",
+        );
+        let nginx = scrape.container("default", "web-0", "nginx").unwrap();
+        assert_eq!(nginx.cpu.usage_seconds, Some(2.0));
+        assert_eq!(nginx.timestamp, Some(Timestamp::UNIX_EPOCH));
+    }
+
+    #[test]
+    fn invalid() {
+        let err = CadvisorScrape::parse(
+            "container_network_receive_bytes_total{container=\"\",namespace=\"default\",pod=\"web-0\"} 1\n",
+            Timestamp::UNIX_EPOCH,
+        )
+        .unwrap_err();
+        assert_eq!(err.line(), 1);
+    }
+
+    #[test]
+    fn unlabelled_network_and_fs_series() {
+        let scrape = scrape(
+            "container_network_tcp_usage_total{container=\"\",namespace=\"default\",pod=\"web-0\",tcp_state=\"established\"} 3\n\
+             container_network_udp_usage_total{container=\"\",namespace=\"default\",pod=\"web-0\",udp_state=\"listen\"} 1\n\
+             container_fs_inodes_free{container=\"nginx\",namespace=\"default\",pod=\"web-0\"} 10\n\
+             container_network_receive_bytes_total{container=\"\",interface=\"eth0\",namespace=\"default\",pod=\"web-0\"} 1\n",
+        );
+        let pod = scrape.pod("default", "web-0").unwrap();
+        assert_eq!(pod.network.keys().collect::<Vec<_>>(), ["eth0"]);
+        assert_eq!(pod.network["eth0"].rx_bytes, Some(1));
+        let nginx = scrape.container("default", "web-0", "nginx").unwrap();
+        assert!(nginx.filesystems.is_empty());
+    }
+
+    #[test]
+    fn throttling() {
+        let previous = scrape(SCRAPE);
+        let current = scrape(
+            &SCRAPE
+                .replace(" 13774 ", " 13874 ")
+                .replace(" 2755 ", " 2835 ")
+                .replace(" 310.25 ", " 312.25 "),
+        );
+
+        let nginx = current.container("default", "web-0", "nginx").unwrap();
+        assert_eq!(nginx.throttled_ratio(), Some(2835.0 / 13874.0));
+
+        let previous = previous.container("default", "web-0", "nginx").unwrap();
+        assert_eq!(nginx.throttled_ratio_since(previous), Some(0.8));
+        assert_eq!(
+            nginx.throttled_since(previous),
+            Some(time::Duration::from_secs(2))
+        );
+        assert_eq!(previous.throttled_ratio_since(nginx), None);
+    }
+
+    #[test]
+    fn join() {
+        let scrape = scrape(SCRAPE);
+        let container = |name: &str| Container {
+            name: name.to_string(),
+            usage: Usage::zero(),
+        };
+        let pod = PodMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some("web-0".to_string()),
+                namespace: Some("default".to_string()),
+                ..default()
+            },
+            containers: vec![container("nginx"), container("istio-proxy")],
+            ..default()
+        };
+
+        let joined = scrape
+            .join(&pod)
+            .map(|(container, stats)| {
+                (
+                    container.name.as_str(),
+                    stats.and_then(|stats| stats.cpu.limit()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            joined,
+            [
+                ("nginx", Some(Cpu::from_millicores(250))),
+                ("istio-proxy", None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Minimal parser of the Prometheus text exposition format, as served by kubelet
+//!
+use super::*;
+
+/// Single sample, e.g. `node_cpu_usage_seconds_total 357.35491 1666186373000`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sample<'a> {
+    pub(crate) line: usize,
+    pub(crate) text: &'a str,
+    pub(crate) name: &'a str,
+    pub(crate) labels: Vec<(&'a str, String)>,
+    pub(crate) value: f64,
+    /// Milliseconds since the epoch
+    ///
+    pub(crate) timestamp: Option<i64>,
+}
+
+impl Sample<'_> {
+    pub(crate) fn error(&self) -> ParseError {
+        ParseError::new(self.line, self.text)
+    }
+
+    pub(crate) fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _)| *label == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Samples of exposition `text`, skipping comments and empty lines
+///
+pub(crate) fn parse(text: &str) -> impl Iterator<Item = Result<Sample<'_>, ParseError>> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| sample(number, line).ok_or_else(|| ParseError::new(number, line)))
+}
+
+fn sample(line: usize, text: &str) -> Option<Sample<'_>> {
+    let end = text
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .unwrap_or(text.len());
+    let (name, rest) = text.split_at(end);
+    if name.is_empty() {
+        return None;
+    }
+
+    let (labels, rest) = match rest.strip_prefix('{') {
+        Some(rest) => labels(rest)?,
+        None => (vec![], rest),
+    };
+
+    let mut fields = rest.split_whitespace();
+    let value = fields.next()?.parse().ok()?;
+    let timestamp = fields.next().map(str::parse).transpose().ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some(Sample {
+        line,
+        text,
+        name,
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// Labels up to the closing `}` and the remaining text
+///
+fn labels(mut text: &str) -> Option<(Vec<(&str, String)>, &str)> {
+    let mut labels = vec![];
+    loop {
+        text = text.trim_start();
+        if let Some(rest) = text.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+
+        let (name, rest) = text.split_once('=')?;
+        let (value, rest) = quoted(rest.trim_start())?;
+        labels.push((name.trim(), value));
+
+        text = rest.trim_start();
+        text = text.strip_prefix(',').unwrap_or(text);
+    }
+}
+
+/// Unescaped content of the quoted string at the start of `text` and the remaining text
+///
+fn quoted(text: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[index + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                escaped => value.push(escaped),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(text: &str) -> Vec<Sample<'_>> {
+        parse(text).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn simple() {
+        let samples = parse_all(
+            "# TYPE scrape_error gauge\n\nscrape_error 0\nnode_load1 1.5e+00 1666186373000\n",
+        );
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].name, "scrape_error");
+        assert_eq!(samples[0].value, 0.0);
+        assert_eq!(samples[0].timestamp, None);
+        assert_eq!(samples[1].line, 4);
+        assert_eq!(samples[1].value, 1.5);
+        assert_eq!(samples[1].timestamp, Some(1666186373000));
+    }
+
+    #[test]
+    fn labels() {
+        let samples = parse_all(
+            r#"container_cpu_usage_seconds_total{container="nginx", namespace="default",pod="web-0",} 0.25 1666186373394"#,
+        );
+        let sample = &samples[0];
+        assert_eq!(sample.label("container"), Some("nginx"));
+        assert_eq!(sample.label("pod"), Some("web-0"));
+        assert_eq!(sample.label("node"), None);
+        assert_eq!(sample.value, 0.25);
+    }
+
+    #[test]
+    fn escaped_labels() {
+        let samples = parse_all(r#"m{path="C:\\temp",msg="say \"hi\"\n",empty=""} NaN"#);
+        let sample = &samples[0];
+        assert_eq!(sample.label("path"), Some(r"C:\temp"));
+        assert_eq!(sample.label("msg"), Some("say \"hi\"\n"));
+        assert_eq!(sample.label("empty"), Some(""));
+        assert!(sample.value.is_nan());
+
+        let samples = parse_all("m{} +Inf");
+        assert_eq!(samples[0].value, f64::INFINITY);
+    }
+
+    #[test]
+    fn invalid() {
+        for text in [
+            "m",
+            "m{a=\"b\" 1",
+            "m{a=b} 1",
+            "m one",
+            "m 1 1.5",
+            "m 1 2 3",
+            "{a=\"b\"} 1",
+        ] {
+            parse(text).next().unwrap().unwrap_err();
+        }
+
+        let err = parse("ok 1\nbroken\n").nth(1).unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid metrics at line 2: broken");
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+
+use v1beta1::{Container, NodeMetrics, PodMetrics, Usage};
+
+use super::*;
+
+/// Namespace and name of a pod
+///
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PodKey {
+    pub namespace: String,
+    pub name: String,
+}
+
+/// Cumulative resource usage of a node or container as reported by kubelet
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceSample {
+    /// Time of the CPU usage sample
+    ///
+    pub timestamp: Timestamp,
+
+    /// Cumulative CPU time consumed, in core-seconds
+    ///
+    pub cpu_usage_seconds: f64,
+
+    /// Current memory working set, in bytes
+    ///
+    pub memory_working_set_bytes: i64,
+
+    /// Start time of the container, not reported for nodes
+    ///
+    pub start_time: Option<Timestamp>,
+}
+
+impl ResourceSample {
+    /// Average CPU usage since `previous` sample and current memory working set,
+    /// along with the length of the window CPU usage was averaged over
+    ///
+    /// If the container restarted after `previous` sample, CPU usage is averaged
+    /// since its start instead. `None` if CPU counter went backwards or no time passed.
+    ///
+    pub fn usage_since(&self, previous: &Self) -> Option<(Usage, time::Duration)> {
+        let (since, cpu_usage_seconds) = match self.start_time {
+            Some(start_time) if start_time > previous.timestamp => (start_time, 0.0),
+            _ => (previous.timestamp, previous.cpu_usage_seconds),
+        };
+
+        let window = time::Duration::try_from(self.timestamp.duration_since(since))
+            .ok()
+            .filter(|window| !window.is_zero())?;
+        let cpu_usage_seconds = self.cpu_usage_seconds - cpu_usage_seconds;
+        if cpu_usage_seconds < 0.0 {
+            return None;
+        }
+
+        let usage = Usage {
+            cpu: Cpu::from_cores(cpu_usage_seconds / window.as_secs_f64()).into(),
+            memory: Memory::from_bytes(self.memory_working_set_bytes).into(),
+        };
+        Some((usage, window))
+    }
+}
+
+/// Single scrape of kubelet `/metrics/resource` endpoint
+///
+/// Kubelet reports cumulative CPU usage, so resource metrics are computed from two
+/// consecutive scrapes, the same way metrics-server does.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceScrape {
+    /// Usage of the node itself
+    ///
+    pub node: Option<ResourceSample>,
+
+    /// Usage of containers, by pod and container name
+    ///
+    pub pods: BTreeMap<PodKey, BTreeMap<String, ResourceSample>>,
+}
+
+/// Sample being assembled from several metrics
+///
+#[derive(Debug, Default)]
+struct Partial {
+    timestamp: Option<Timestamp>,
+    cpu_usage_seconds: Option<f64>,
+    memory_working_set_bytes: Option<f64>,
+    start_time: Option<Timestamp>,
+}
+
+impl Partial {
+    fn complete(self) -> Option<ResourceSample> {
+        Some(ResourceSample {
+            timestamp: self.timestamp?,
+            cpu_usage_seconds: self.cpu_usage_seconds?,
+            memory_working_set_bytes: self.memory_working_set_bytes? as i64,
+            start_time: self.start_time,
+        })
+    }
+}
+
+impl ResourceScrape {
+    /// Parse kubelet response `text`, scraped at `scraped_at`
+    ///
+    /// `scraped_at` is used for samples without explicit timestamp. Containers
+    /// missing either CPU or memory usage are skipped.
+    ///
+    pub fn parse(text: &str, scraped_at: Timestamp) -> Result<Self, ParseError> {
+        let mut node = Partial::default();
+        let mut containers = BTreeMap::<(PodKey, String), Partial>::new();
+
+        for sample in prometheus::parse(text) {
+            let sample = sample?;
+            let timestamp = match sample.timestamp {
+                Some(millis) => Timestamp::from_millisecond(millis).map_err(|_| sample.error())?,
+                None => scraped_at,
+            };
+
+            let partial = if sample.name.starts_with("node_") {
+                &mut node
+            } else if sample.name.starts_with("container_") {
+                let key = match (
+                    sample.label("namespace"),
+                    sample.label("pod"),
+                    sample.label("container"),
+                ) {
+                    (Some(namespace), Some(name), Some(container)) => {
+                        let namespace = namespace.to_string();
+                        let name = name.to_string();
+                        (PodKey { namespace, name }, container.to_string())
+                    }
+                    _ => continue,
+                };
+                containers.entry(key).or_default()
+            } else {
+                continue;
+            };
+
+            match sample.name {
+                "node_cpu_usage_seconds_total" | "container_cpu_usage_seconds_total" => {
+                    partial.timestamp = Some(timestamp);
+                    partial.cpu_usage_seconds = Some(sample.value);
+                }
+                "node_memory_working_set_bytes" | "container_memory_working_set_bytes" => {
+                    partial.memory_working_set_bytes = Some(sample.value);
+                }
+                "container_start_time_seconds" => {
+                    // Kubelet reports start time in seconds, with millisecond precision
+                    let millis = (sample.value * 1000.0).round() as i64;
+                    let start_time =
+                        Timestamp::from_millisecond(millis).map_err(|_| sample.error())?;
+                    partial.start_time = Some(start_time);
+                }
+                _ => {}
+            }
+        }
+
+        let mut pods = BTreeMap::<PodKey, BTreeMap<_, _>>::new();
+        for ((pod, container), partial) in containers {
+            if let Some(sample) = partial.complete() {
+                pods.entry(pod).or_default().insert(container, sample);
+            }
+        }
+
+        let node = node.complete();
+        Ok(Self { node, pods })
+    }
+
+    /// Metrics of node `name` since `previous` scrape
+    ///
+    pub fn node_metrics(&self, previous: &Self, name: &str) -> Option<NodeMetrics> {
+        let current = self.node.as_ref()?;
+        let (usage, window) = current.usage_since(previous.node.as_ref()?)?;
+        let metadata = metav1::ObjectMeta {
+            name: Some(name.to_string()),
+            ..default()
+        };
+        Some(NodeMetrics {
+            metadata,
+            timestamp: metav1::Time(current.timestamp),
+            window,
+            usage,
+        })
+    }
+
+    /// Metrics of all pods since `previous` scrape
+    ///
+    /// Pods with any container missing from `previous` scrape are skipped. Pod
+    /// timestamp and window are the ones of its earliest container sample.
+    ///
+    pub fn pod_metrics(&self, previous: &Self) -> Vec<PodMetrics> {
+        self.pods
+            .iter()
+            .filter_map(|(pod, containers)| {
+                let previous = previous.pods.get(pod)?;
+                pod_metrics(pod, containers, previous)
+            })
+            .collect()
+    }
+}
+
+fn pod_metrics(
+    pod: &PodKey,
+    containers: &BTreeMap<String, ResourceSample>,
+    previous: &BTreeMap<String, ResourceSample>,
+) -> Option<PodMetrics> {
+    let mut earliest = None::<(Timestamp, time::Duration)>;
+    let containers = containers
+        .iter()
+        .map(|(name, current)| {
+            let (usage, window) = current.usage_since(previous.get(name)?)?;
+            if earliest.is_none_or(|(timestamp, _)| current.timestamp < timestamp) {
+                earliest = Some((current.timestamp, window));
+            }
+            let name = name.clone();
+            Some(Container { name, usage })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let (timestamp, window) = earliest?;
+
+    let metadata = metav1::ObjectMeta {
+        name: Some(pod.name.clone()),
+        namespace: Some(pod.namespace.clone()),
+        ..default()
+    };
+    Some(PodMetrics {
+        metadata,
+        containers,
+        timestamp: metav1::Time(timestamp),
+        window,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRAPE: &str = include_str!("../../testdata/kubelet-metrics-resource.txt");
+
+    /// Ten seconds later: `nginx` used half a core, `coredns` restarted and `db-0` started
+    const NEXT_SCRAPE: &str = r#"
+container_cpu_usage_seconds_total{container="coredns",namespace="kube-system",pod="coredns-5d78c9869d-7xkqp"} 0.5 1666186383394
+container_cpu_usage_seconds_total{container="nginx",namespace="default",pod="web-0"} 125.5 1666186383120
+container_cpu_usage_seconds_total{container="sidecar",namespace="default",pod="web-0"} 3.35 1666186383250
+container_cpu_usage_seconds_total{container="postgres",namespace="default",pod="db-0"} 0.1 1666186383000
+container_memory_working_set_bytes{container="coredns",namespace="kube-system",pod="coredns-5d78c9869d-7xkqp"} 1.048576e+07 1666186383394
+container_memory_working_set_bytes{container="nginx",namespace="default",pod="web-0"} 7.340032e+07 1666186383120
+container_memory_working_set_bytes{container="sidecar",namespace="default",pod="web-0"} 8.388608e+06 1666186383250
+container_memory_working_set_bytes{container="postgres",namespace="default",pod="db-0"} 1.34217728e+08 1666186383000
+container_start_time_seconds{container="coredns",namespace="kube-system",pod="coredns-5d78c9869d-7xkqp"} 1.666186378394e+09 1666186378394
+node_cpu_usage_seconds_total 377.35491 1666186388000
+node_memory_working_set_bytes 1.7179869184e+09 1666186388000
+"#;
+
+    fn scrape(text: &str) -> ResourceScrape {
+        ResourceScrape::parse(text, Timestamp::UNIX_EPOCH).unwrap()
+    }
+
+    fn key(namespace: &str, name: &str) -> PodKey {
+        let namespace = namespace.to_string();
+        let name = name.to_string();
+        PodKey { namespace, name }
+    }
+
+    #[test]
+    fn parse() {
+        let scrape = scrape(SCRAPE);
+
+        let node = scrape.node.as_ref().unwrap();
+        assert_eq!(node.timestamp.as_millisecond(), 1666186373000);
+        assert_eq!(node.cpu_usage_seconds, 357.35491);
+        assert_eq!(node.memory_working_set_bytes, 1616273408);
+        assert_eq!(node.start_time, None);
+
+        assert_eq!(scrape.pods.len(), 2);
+        let web = &scrape.pods[&key("default", "web-0")];
+        assert_eq!(web.keys().collect::<Vec<_>>(), ["nginx", "sidecar"]);
+        let nginx = &web["nginx"];
+        assert_eq!(nginx.timestamp.as_millisecond(), 1666186373120);
+        assert_eq!(nginx.memory_working_set_bytes, 64 << 20);
+        assert_eq!(nginx.start_time.unwrap().as_second(), 1666185000);
+    }
+
+    #[test]
+    fn parse_incomplete() {
+        let scrape = scrape(
+            "container_cpu_usage_seconds_total{container=\"nginx\",namespace=\"default\",pod=\"web-0\"} 1\n\
+             container_cpu_usage_seconds_total{namespace=\"default\",pod=\"web-0\"} 1\n\
+             node_cpu_usage_seconds_total 1\n\
+             node_memory_working_set_bytes 1024\n",
+        );
+        assert!(scrape.pods.is_empty());
+        assert_eq!(scrape.node.unwrap().timestamp, Timestamp::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let err = ResourceScrape::parse(
+            "node_cpu_usage_seconds_total 1 99999999999999999\n",
+            Timestamp::UNIX_EPOCH,
+        )
+        .unwrap_err();
+        assert_eq!(err.line(), 1);
+    }
+
+    #[test]
+    fn node_metrics() {
+        let (previous, current) = (scrape(SCRAPE), scrape(NEXT_SCRAPE));
+        let node = current.node_metrics(&previous, "node-1").unwrap();
+        assert_eq!(node.metadata.name.as_deref(), Some("node-1"));
+        assert_eq!(node.timestamp.0.as_millisecond(), 1666186388000);
+        assert_eq!(node.window, time::Duration::from_secs(15));
+        assert_eq!(node.cpu().unwrap().millicores(), 1334);
+        assert_eq!(node.memory().unwrap(), Memory::from_bytes(1717986918));
+
+        assert!(previous.node_metrics(&current, "node-1").is_none());
+        assert!(current.node_metrics(&current, "node-1").is_none());
+    }
+
+    #[test]
+    fn pod_metrics() {
+        let (previous, current) = (scrape(SCRAPE), scrape(NEXT_SCRAPE));
+        let pods = current.pod_metrics(&previous);
+        assert_eq!(pods.len(), 2);
+
+        let web = &pods[0];
+        assert_eq!(web.metadata.name.as_deref(), Some("web-0"));
+        assert_eq!(web.timestamp.0.as_millisecond(), 1666186383120);
+        assert_eq!(web.window, time::Duration::from_secs(10));
+        assert_eq!(web.containers[0].cpu().unwrap(), Cpu::from_millicores(500));
+        assert_eq!(web.containers[1].cpu().unwrap(), Cpu::from_millicores(10));
+        assert_eq!(web.memory().unwrap(), Memory::from_mib(78));
+
+        let coredns = &pods[1];
+        assert_eq!(coredns.metadata.namespace.as_deref(), Some("kube-system"));
+        assert_eq!(coredns.window, time::Duration::from_secs(5));
+        assert_eq!(coredns.cpu().unwrap(), Cpu::from_millicores(100));
+        assert_eq!(coredns.memory().unwrap(), Memory::from_mib(10));
+    }
+}
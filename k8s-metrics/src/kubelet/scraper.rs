@@ -0,0 +1,557 @@
+//! Periodic scraping of kubelet resource metrics, the way metrics-server does it
+//!
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::ControlFlow;
+
+use tokio::task::JoinSet;
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+use super::*;
+
+/// Kubelet endpoint serving `/metrics/resource` of `node`
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub node: String,
+    /// Path of the endpoint, relative to the cluster URL of the client
+    ///
+    pub path: String,
+}
+
+impl Target {
+    pub fn new(node: &str, path: &str) -> Self {
+        let node = node.to_string();
+        let path = path.to_string();
+        Self { node, path }
+    }
+
+    /// `/metrics/resource` of `node`, proxied by the API server
+    ///
+    pub fn node(node: &str) -> Self {
+        Self::new(
+            node,
+            &format!("/api/v1/nodes/{node}/proxy/metrics/resource"),
+        )
+    }
+
+    /// Targets of all nodes of the cluster, proxied by the API server
+    ///
+    pub async fn all_nodes(client: &kube::Client) -> Result<Vec<Self>, kube::Error> {
+        let nodes = kube::Api::<corev1::Node>::all(client.clone())
+            .list_metadata(&default())
+            .await?;
+        let targets = nodes
+            .items
+            .iter()
+            .filter_map(|node| node.metadata.name.as_deref())
+            .map(Self::node)
+            .collect();
+        Ok(targets)
+    }
+}
+
+/// Outcome of a single scraping round
+///
+#[derive(Debug, Default)]
+pub struct ScrapeReport {
+    pub nodes: Vec<NodeMetrics>,
+    pub pods: Vec<PodMetrics>,
+    /// Failures by node name, metrics of these nodes are served from earlier scrapes until stale
+    ///
+    pub errors: BTreeMap<String, ScrapeError>,
+}
+
+/// Scrapes kubelet `/metrics/resource` endpoints and computes resource metrics
+///
+/// CPU usage is averaged between the two latest distinct samples of each node and
+/// container, so metrics show up from the second scrape on. As with metrics-server,
+/// pods with containers without two samples are left out, as are containers whose
+/// CPU counter went backwards.
+///
+pub struct Scraper {
+    client: kube::Client,
+    targets: Vec<Target>,
+    timeout: time::Duration,
+    stale_after: time::Duration,
+    nodes: BTreeMap<String, NodeState>,
+}
+
+impl fmt::Debug for Scraper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scraper")
+            .field("targets", &self.targets)
+            .field("timeout", &self.timeout)
+            .field("stale_after", &self.stale_after)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scraper {
+    pub fn new(client: kube::Client, targets: Vec<Target>) -> Self {
+        Self {
+            client,
+            targets,
+            timeout: time::Duration::from_secs(10),
+            stale_after: time::Duration::from_secs(60),
+            nodes: default(),
+        }
+    }
+
+    /// Timeout of a single scrape, 10 seconds by default
+    ///
+    pub fn timeout(self, timeout: time::Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// How long metrics of nodes failing to be scraped are still reported, a minute by default
+    ///
+    pub fn stale_after(self, stale_after: time::Duration) -> Self {
+        Self {
+            stale_after,
+            ..self
+        }
+    }
+
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    /// Replace scraped targets, e.g. when nodes join or leave the cluster
+    ///
+    pub fn set_targets(&mut self, targets: Vec<Target>) {
+        self.nodes
+            .retain(|node, _| targets.iter().any(|target| target.node == *node));
+        self.targets = targets;
+    }
+
+    /// Scrape all targets concurrently and compute metrics from the samples collected so far
+    ///
+    pub async fn scrape(&mut self) -> ScrapeReport {
+        let mut tasks = JoinSet::new();
+        for target in &self.targets {
+            let client = self.client.clone();
+            let target = target.clone();
+            let timeout = self.timeout;
+            tasks.spawn(async move {
+                let scrape = fetch(&client, &target.path, timeout).await;
+                (target.node, scrape)
+            });
+        }
+
+        let mut errors = BTreeMap::new();
+        for (node, scrape) in tasks.join_all().await {
+            match scrape {
+                Ok((scrape, scraped_at)) => {
+                    self.nodes
+                        .entry(node)
+                        .or_default()
+                        .store(scrape, scraped_at);
+                }
+                Err(err) => {
+                    errors.insert(node, err);
+                }
+            }
+        }
+
+        let now = Timestamp::now();
+        let stale_after = self.stale_after;
+        self.nodes.retain(|node, state| {
+            let age = time::Duration::try_from(now.duration_since(state.scraped_at));
+            !errors.contains_key(node) || age.unwrap_or_default() <= stale_after
+        });
+
+        let mut report = ScrapeReport {
+            errors,
+            ..default()
+        };
+        for (node, state) in &self.nodes {
+            report
+                .nodes
+                .extend(state.latest.node_metrics(&state.previous, node));
+            report
+                .pods
+                .extend(state.latest.pod_metrics(&state.previous));
+        }
+        report
+    }
+
+    /// Scrape all targets every `interval`, until `f` breaks out of the loop
+    ///
+    pub async fn run<F>(&mut self, interval: time::Duration, mut f: F)
+    where
+        F: FnMut(ScrapeReport) -> ControlFlow<()>,
+    {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if f(self.scrape().await).is_break() {
+                break;
+            }
+        }
+    }
+}
+
+/// Failure to scrape a single target
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ScrapeError {
+    #[error("Invalid target: {0}")]
+    Target(#[from] http::Error),
+
+    #[error("Scrape timed out after {0:?}")]
+    Timeout(time::Duration),
+
+    #[error(transparent)]
+    Kube(#[from] kube::Error),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+async fn fetch(
+    client: &kube::Client,
+    path: &str,
+    timeout: time::Duration,
+) -> Result<(ResourceScrape, Timestamp), ScrapeError> {
+    let request = http::Request::get(path)
+        .header(http::header::ACCEPT, "text/plain")
+        .body(vec![])?;
+    let text = tokio::time::timeout(timeout, client.request_text(request))
+        .await
+        .map_err(|_| ScrapeError::Timeout(timeout))??;
+    let scraped_at = Timestamp::now();
+    let scrape = ResourceScrape::parse(&text, scraped_at)?;
+    Ok((scrape, scraped_at))
+}
+
+/// Two latest distinct samples of a node and its containers
+///
+#[derive(Debug)]
+struct NodeState {
+    previous: ResourceScrape,
+    latest: ResourceScrape,
+    scraped_at: Timestamp,
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        Self {
+            previous: default(),
+            latest: default(),
+            scraped_at: Timestamp::UNIX_EPOCH,
+        }
+    }
+}
+
+impl NodeState {
+    fn store(&mut self, current: ResourceScrape, scraped_at: Timestamp) {
+        let mut previous = std::mem::take(&mut self.previous);
+        let mut latest = std::mem::take(&mut self.latest);
+
+        if let Some(node) = current.node {
+            let (prev, last) = advance(previous.node, latest.node, node);
+            self.previous.node = prev;
+            self.latest.node = Some(last);
+        }
+
+        for (pod, containers) in current.pods {
+            let mut previous = previous.pods.remove(&pod).unwrap_or_default();
+            let mut latest = latest.pods.remove(&pod).unwrap_or_default();
+            for (name, sample) in containers {
+                let (prev, last) = advance(previous.remove(&name), latest.remove(&name), sample);
+                if let Some(prev) = prev {
+                    self.previous
+                        .pods
+                        .entry(pod.clone())
+                        .or_default()
+                        .insert(name.clone(), prev);
+                }
+                self.latest
+                    .pods
+                    .entry(pod.clone())
+                    .or_default()
+                    .insert(name, last);
+            }
+        }
+
+        self.scraped_at = scraped_at;
+    }
+}
+
+/// Previous and latest sample after receiving `current` one
+///
+/// Kubelet caches its stats, so scrapes close to each other may return the same sample.
+///
+fn advance(
+    previous: Option<ResourceSample>,
+    latest: Option<ResourceSample>,
+    current: ResourceSample,
+) -> (Option<ResourceSample>, ResourceSample) {
+    match latest {
+        Some(latest) if latest.timestamp < current.timestamp => (Some(latest), current),
+        Some(latest) if latest.timestamp == current.timestamp => (previous, latest),
+        _ => (None, current),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    type Responses = Arc<Mutex<BTreeMap<String, VecDeque<(u16, String)>>>>;
+
+    /// Local HTTP server answering each path with queued responses, 404 once exhausted
+    ///
+    async fn stand_in() -> (kube::Client, Responses) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = Responses::default();
+
+        let queued = responses.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let queued = queued.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut request = String::new();
+                    stream.read_line(&mut request).await.unwrap();
+                    let mut header = String::new();
+                    while stream.read_line(&mut header).await.unwrap() > 2 {
+                        header.clear();
+                    }
+
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let (code, body) = queued
+                        .lock()
+                        .unwrap()
+                        .get_mut(path)
+                        .and_then(VecDeque::pop_front)
+                        .unwrap_or((404, "404 page not found".to_string()));
+                    let response = format!(
+                        "HTTP/1.1 {code} Canned\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        let config = kube::Config::new(url.parse().unwrap());
+        let client = kube::Client::try_from(config).unwrap();
+        (client, responses)
+    }
+
+    fn queue(responses: &Responses, path: &str, code: u16, body: &str) {
+        responses
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .push_back((code, body.to_string()));
+    }
+
+    fn scrape(node_cpu: f64, container_cpu: f64, second: i64) -> String {
+        let millis = 1666186370000 + second * 1000;
+        format!(
+            "node_cpu_usage_seconds_total {node_cpu} {millis}\n\
+             node_memory_working_set_bytes 1.073741824e+09 {millis}\n\
+             container_cpu_usage_seconds_total{{container=\"nginx\",namespace=\"default\",pod=\"web-0\"}} {container_cpu} {millis}\n\
+             container_memory_working_set_bytes{{container=\"nginx\",namespace=\"default\",pod=\"web-0\"}} 6.7108864e+07 {millis}\n"
+        )
+    }
+
+    fn targets() -> Vec<Target> {
+        vec![
+            Target::new("node-1", "/node-1/metrics/resource"),
+            Target::new("node-2", "/node-2/metrics/resource"),
+        ]
+    }
+
+    #[test]
+    fn node_target() {
+        assert_eq!(
+            Target::node("kind-worker").path,
+            "/api/v1/nodes/kind-worker/proxy/metrics/resource"
+        );
+    }
+
+    #[tokio::test]
+    async fn rates() {
+        let (client, responses) = stand_in().await;
+        let mut scraper = Scraper::new(client, targets());
+
+        queue(
+            &responses,
+            "/node-1/metrics/resource",
+            200,
+            &scrape(100.0, 10.0, 0),
+        );
+        queue(
+            &responses,
+            "/node-2/metrics/resource",
+            200,
+            &scrape(500.0, 50.0, 0),
+        );
+        let report = scraper.scrape().await;
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.nodes.is_empty());
+        assert!(report.pods.is_empty());
+
+        queue(
+            &responses,
+            "/node-1/metrics/resource",
+            200,
+            &scrape(115.0, 12.5, 10),
+        );
+        queue(
+            &responses,
+            "/node-2/metrics/resource",
+            500,
+            "kubelet is down",
+        );
+        let report = scraper.scrape().await;
+        assert_eq!(report.nodes.len(), 1);
+        let node = &report.nodes[0];
+        assert_eq!(node.metadata.name.as_deref(), Some("node-1"));
+        assert_eq!(node.timestamp.0.as_millisecond(), 1666186380000);
+        assert_eq!(node.window, time::Duration::from_secs(10));
+        assert_eq!(node.cpu().unwrap(), Cpu::from_millicores(1500));
+        assert_eq!(node.memory().unwrap(), Memory::from_gib(1));
+        assert_eq!(report.pods.len(), 1);
+        assert_eq!(report.pods[0].cpu().unwrap(), Cpu::from_millicores(250));
+        assert!(matches!(
+            &report.errors["node-2"],
+            ScrapeError::Kube(kube::Error::Api(status)) if status.code == 500
+        ));
+
+        // Kubelet returned cached stats, metrics are still served
+        queue(
+            &responses,
+            "/node-1/metrics/resource",
+            200,
+            &scrape(115.0, 12.5, 10),
+        );
+        queue(
+            &responses,
+            "/node-2/metrics/resource",
+            200,
+            &scrape(520.0, 55.0, 20),
+        );
+        let report = scraper.scrape().await;
+        assert_eq!(report.nodes.len(), 2);
+        assert_eq!(report.nodes[0].cpu().unwrap(), Cpu::from_millicores(1500));
+        assert_eq!(report.nodes[1].window, time::Duration::from_secs(20));
+        assert_eq!(report.nodes[1].cpu().unwrap(), Cpu::from_millicores(1000));
+        assert_eq!(report.pods.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn counter_reset() {
+        let (client, responses) = stand_in().await;
+        let mut scraper = Scraper::new(client, targets()[..1].to_vec());
+        for (container_cpu, second) in [(10.0, 0), (12.0, 10), (1.0, 20), (2.0, 30)] {
+            queue(
+                &responses,
+                "/node-1/metrics/resource",
+                200,
+                &scrape(100.0 + second as f64, container_cpu, second),
+            );
+        }
+
+        let pods = [
+            scraper.scrape().await.pods.len(),
+            scraper.scrape().await.pods.len(),
+            scraper.scrape().await.pods.len(),
+            scraper.scrape().await.pods.len(),
+        ];
+        assert_eq!(pods, [0, 1, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn stale_nodes() {
+        let (client, responses) = stand_in().await;
+        let mut scraper =
+            Scraper::new(client, targets()[..1].to_vec()).stale_after(time::Duration::ZERO);
+        queue(
+            &responses,
+            "/node-1/metrics/resource",
+            200,
+            &scrape(100.0, 10.0, 0),
+        );
+        queue(
+            &responses,
+            "/node-1/metrics/resource",
+            200,
+            &scrape(110.0, 11.0, 10),
+        );
+        scraper.scrape().await;
+        assert_eq!(scraper.scrape().await.nodes.len(), 1);
+
+        tokio::time::sleep(time::Duration::from_millis(5)).await;
+        let report = scraper.scrape().await;
+        assert!(report.nodes.is_empty());
+        assert!(report.pods.is_empty());
+        assert!(matches!(
+            &report.errors["node-1"],
+            ScrapeError::Kube(kube::Error::Api(status)) if status.code == 404
+        ));
+    }
+
+    #[tokio::test]
+    async fn targets_change() {
+        let (client, responses) = stand_in().await;
+        let mut scraper = Scraper::new(client, targets());
+        for (node, cpu) in [("node-1", 100.0), ("node-2", 500.0)] {
+            let path = format!("/{node}/metrics/resource");
+            queue(&responses, &path, 200, &scrape(cpu, 10.0, 0));
+            queue(&responses, &path, 200, &scrape(cpu + 10.0, 11.0, 10));
+        }
+        scraper.scrape().await;
+
+        scraper.set_targets(targets()[1..].to_vec());
+        let report = scraper.scrape().await;
+        let nodes = report
+            .nodes
+            .iter()
+            .filter_map(|node| node.metadata.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(nodes, ["node-2"]);
+    }
+
+    #[tokio::test]
+    async fn run() {
+        let (client, responses) = stand_in().await;
+        let mut scraper = Scraper::new(client, targets()[..1].to_vec());
+        for second in [0, 10, 20] {
+            queue(
+                &responses,
+                "/node-1/metrics/resource",
+                200,
+                &scrape(100.0 + second as f64, 10.0, second),
+            );
+        }
+
+        let mut reports = vec![];
+        scraper
+            .run(time::Duration::from_millis(1), |report| {
+                reports.push(report.nodes.len());
+                if reports.len() == 3 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .await;
+        assert_eq!(reports, [0, 1, 1]);
+    }
+}
@@ -0,0 +1,550 @@
+//! Kubelet `/stats/summary` API, `stats.kubelet.k8s.io/v1alpha1`
+//!
+use std::collections::BTreeMap;
+
+use v1beta1::{Container, NodeMetrics, PodMetrics, Usage};
+
+use super::*;
+
+/// Summary of node and pod statistics collected by kubelet
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub node: NodeStats,
+    #[serde(default)]
+    pub pods: Vec<PodStats>,
+}
+
+impl Summary {
+    /// Metrics of this node, with CPU usage averaged by kubelet over `window`
+    ///
+    pub fn node_metrics(&self, window: time::Duration) -> Option<NodeMetrics> {
+        self.node.metrics(window)
+    }
+
+    /// Metrics of all pods with complete usage, with CPU usage averaged by kubelet over `window`
+    ///
+    pub fn pod_metrics(&self, window: time::Duration) -> Vec<PodMetrics> {
+        self.pods
+            .iter()
+            .filter_map(|pod| pod.metrics(window))
+            .collect()
+    }
+
+    /// Statistics of pod `name` in `namespace`
+    ///
+    pub fn pod(&self, namespace: &str, name: &str) -> Option<&PodStats> {
+        self.pods
+            .iter()
+            .find(|pod| pod.pod_ref.namespace == namespace && pod.pod_ref.name == name)
+    }
+}
+
+/// Statistics of the node itself
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStats {
+    pub node_name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub system_containers: Vec<ContainerStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<metav1::Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fs: Option<FsStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rlimit: Option<RlimitStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap: Option<SwapStats>,
+}
+
+impl NodeStats {
+    /// Current CPU usage and memory working set
+    ///
+    pub fn usage(&self) -> Option<Usage> {
+        usage(self.cpu.as_ref()?, self.memory.as_ref()?)
+    }
+
+    /// Metrics of this node, with CPU usage averaged by kubelet over `window`
+    ///
+    pub fn metrics(&self, window: time::Duration) -> Option<NodeMetrics> {
+        let metadata = metav1::ObjectMeta {
+            name: Some(self.node_name.clone()),
+            ..default()
+        };
+        Some(NodeMetrics {
+            metadata,
+            timestamp: self.cpu.as_ref()?.time.clone()?,
+            window,
+            usage: self.usage()?,
+        })
+    }
+
+    /// Statistics of system container `name`, e.g. `kubelet` or `runtime`
+    ///
+    pub fn system_container(&self, name: &str) -> Option<&ContainerStats> {
+        self.system_containers
+            .iter()
+            .find(|container| container.name == name)
+    }
+}
+
+/// Statistics of a pod and its containers
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodStats {
+    pub pod_ref: PodReference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<metav1::Time>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<ContainerStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volume: Vec<VolumeStats>,
+    #[serde(rename = "ephemeral-storage", skip_serializing_if = "Option::is_none")]
+    pub ephemeral_storage: Option<FsStats>,
+    #[serde(rename = "process_stats", skip_serializing_if = "Option::is_none")]
+    pub process_stats: Option<ProcessStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap: Option<SwapStats>,
+}
+
+impl PodStats {
+    /// Metrics of this pod, with CPU usage averaged by kubelet over `window`
+    ///
+    /// `None` unless usage of all containers is known. Timestamp is the one of
+    /// the earliest container CPU sample.
+    ///
+    pub fn metrics(&self, window: time::Duration) -> Option<PodMetrics> {
+        let containers = self
+            .containers
+            .iter()
+            .map(|container| {
+                let name = container.name.clone();
+                let usage = container.usage()?;
+                Some(Container { name, usage })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let timestamp = self
+            .containers
+            .iter()
+            .filter_map(|container| container.cpu.as_ref()?.time.clone())
+            .min_by_key(|time| time.0)?;
+
+        let metadata = metav1::ObjectMeta {
+            name: Some(self.pod_ref.name.clone()),
+            namespace: Some(self.pod_ref.namespace.clone()),
+            uid: Some(self.pod_ref.uid.clone()).filter(|uid| !uid.is_empty()),
+            ..default()
+        };
+        Some(PodMetrics {
+            metadata,
+            containers,
+            timestamp,
+            window,
+        })
+    }
+
+    /// Statistics of container `name`
+    ///
+    pub fn container(&self, name: &str) -> Option<&ContainerStats> {
+        self.containers
+            .iter()
+            .find(|container| container.name == name)
+    }
+}
+
+/// Reference to the pod statistics belong to
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodReference {
+    pub name: String,
+    pub namespace: String,
+    #[serde(default)]
+    pub uid: String,
+}
+
+/// Statistics of a container
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerStats {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<metav1::Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rootfs: Option<FsStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<FsStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap: Option<SwapStats>,
+}
+
+impl ContainerStats {
+    /// Current CPU usage and memory working set
+    ///
+    pub fn usage(&self) -> Option<Usage> {
+        usage(self.cpu.as_ref()?, self.memory.as_ref()?)
+    }
+}
+
+/// CPU usage
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuStats {
+    pub time: Option<metav1::Time>,
+    /// Average CPU usage over the last sampling interval
+    ///
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_nano_cores: Option<u64>,
+    /// Cumulative CPU usage since the start of the container
+    ///
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_core_nano_seconds: Option<u64>,
+}
+
+impl CpuStats {
+    /// Average CPU usage over the last sampling interval
+    ///
+    pub fn cpu(&self) -> Option<Cpu> {
+        let nanocores = self.usage_nano_cores?.try_into().ok()?;
+        Some(Cpu::from_nanocores(nanocores))
+    }
+}
+
+/// Memory usage
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    pub time: Option<metav1::Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_bytes: Option<u64>,
+    /// Memory in use that cannot be evicted, as reported by `kubectl top`
+    ///
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_set_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_faults: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub major_page_faults: Option<u64>,
+}
+
+impl MemoryStats {
+    /// Memory working set
+    ///
+    pub fn memory(&self) -> Option<Memory> {
+        let bytes = self.working_set_bytes?.try_into().ok()?;
+        Some(Memory::from_bytes(bytes))
+    }
+}
+
+/// Network usage of the default interface and all interfaces
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub time: Option<metav1::Time>,
+    #[serde(flatten)]
+    pub default_interface: InterfaceStats,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<InterfaceStats>,
+}
+
+/// Network usage of a single interface
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceStats {
+    #[serde(default)]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rx_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rx_errors: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_errors: Option<u64>,
+}
+
+/// Filesystem usage
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsStats {
+    pub time: Option<metav1::Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inodes_free: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inodes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inodes_used: Option<u64>,
+}
+
+/// Filesystems used by the container runtime
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_fs: Option<FsStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_fs: Option<FsStats>,
+}
+
+/// Process limits of the node
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RlimitStats {
+    pub time: Option<metav1::Time>,
+    #[serde(rename = "maxpid", skip_serializing_if = "Option::is_none")]
+    pub max_pid: Option<i64>,
+    #[serde(rename = "curproc", skip_serializing_if = "Option::is_none")]
+    pub num_of_running_processes: Option<i64>,
+}
+
+/// Usage of a pod volume
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeStats {
+    #[serde(flatten)]
+    pub fs: FsStats,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pvc_ref: Option<PvcReference>,
+}
+
+/// Reference to the persistent volume claim backing a volume
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PvcReference {
+    pub name: String,
+    pub namespace: String,
+}
+
+/// Process statistics of a pod
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessStats {
+    #[serde(rename = "process_count", skip_serializing_if = "Option::is_none")]
+    pub process_count: Option<u64>,
+}
+
+/// Swap usage
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapStats {
+    pub time: Option<metav1::Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_available_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_usage_bytes: Option<u64>,
+}
+
+fn usage(cpu: &CpuStats, memory: &MemoryStats) -> Option<Usage> {
+    Some(Usage {
+        cpu: cpu.cpu()?.into(),
+        memory: memory.memory()?.into(),
+    })
+}
+
+/// Cumulative usage for computing metrics from two consecutive summaries,
+/// see `ResourceScrape::node_metrics()` and `ResourceScrape::pod_metrics()`
+///
+impl From<&Summary> for ResourceScrape {
+    fn from(summary: &Summary) -> Self {
+        let node = sample(
+            summary.node.cpu.as_ref(),
+            summary.node.memory.as_ref(),
+            None,
+        );
+        let pods = summary
+            .pods
+            .iter()
+            .map(|pod| {
+                let key = PodKey {
+                    namespace: pod.pod_ref.namespace.clone(),
+                    name: pod.pod_ref.name.clone(),
+                };
+                let containers = pod
+                    .containers
+                    .iter()
+                    .filter_map(|container| {
+                        let start_time = container.start_time.as_ref().map(|time| time.0);
+                        let sample = sample(
+                            container.cpu.as_ref(),
+                            container.memory.as_ref(),
+                            start_time,
+                        )?;
+                        Some((container.name.clone(), sample))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                (key, containers)
+            })
+            .filter(|(_, containers)| !containers.is_empty())
+            .collect();
+        Self { node, pods }
+    }
+}
+
+fn sample(
+    cpu: Option<&CpuStats>,
+    memory: Option<&MemoryStats>,
+    start_time: Option<Timestamp>,
+) -> Option<ResourceSample> {
+    let cpu = cpu?;
+    Some(ResourceSample {
+        timestamp: cpu.time.as_ref()?.0,
+        cpu_usage_seconds: cpu.usage_core_nano_seconds? as f64 / 1e9,
+        memory_working_set_bytes: memory?.memory()?.bytes(),
+        start_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s::serde_json as json;
+
+    use super::*;
+
+    const SUMMARY: &str = include_str!("../../testdata/kubelet-stats-summary.json");
+
+    fn summary() -> Summary {
+        json::from_str(SUMMARY).unwrap()
+    }
+
+    #[test]
+    fn deserialize() {
+        let summary = summary();
+
+        let node = &summary.node;
+        assert_eq!(node.node_name, "kind-control-plane");
+        assert_eq!(
+            node.system_container("kubelet")
+                .and_then(|kubelet| kubelet.memory.as_ref()?.rss_bytes),
+            Some(36085760)
+        );
+        let network = node.network.as_ref().unwrap();
+        assert_eq!(network.default_interface.name, "eth0");
+        assert_eq!(network.interfaces[0].rx_bytes, Some(54821456));
+        assert_eq!(node.rlimit.as_ref().unwrap().max_pid, Some(4194304));
+        let image_fs = node.runtime.as_ref().unwrap().image_fs.as_ref().unwrap();
+        assert_eq!(image_fs.used_bytes, Some(1044004864));
+
+        let web = summary.pod("default", "web-0").unwrap();
+        assert_eq!(
+            web.ephemeral_storage.as_ref().unwrap().used_bytes,
+            Some(69632)
+        );
+        assert_eq!(web.process_stats.as_ref().unwrap().process_count, Some(5));
+        assert_eq!(web.volume[1].fs.used_bytes, Some(104857600));
+        assert_eq!(web.volume[1].pvc_ref.as_ref().unwrap().name, "data-web-0");
+        let nginx = web.container("nginx").unwrap();
+        assert_eq!(nginx.memory.as_ref().unwrap().page_faults, Some(4125));
+        assert_eq!(nginx.logs.as_ref().unwrap().used_bytes, Some(16384));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let summary = summary();
+        let value = json::to_value(&summary).unwrap();
+        assert_eq!(value["pods"][0]["ephemeral-storage"]["usedBytes"], 69632);
+        assert_eq!(value["pods"][0]["network"]["rxBytes"], 1052338);
+        assert_eq!(value["pods"][1].get("volume"), None);
+        assert_eq!(json::from_value::<Summary>(value).unwrap(), summary);
+    }
+
+    #[test]
+    fn node_metrics() {
+        let window = time::Duration::from_secs(10);
+        let node = summary().node_metrics(window).unwrap();
+        assert_eq!(node.metadata.name.as_deref(), Some("kind-control-plane"));
+        assert_eq!(node.timestamp.0.to_string(), "2022-10-19T13:33:08Z");
+        assert_eq!(node.window, window);
+        assert_eq!(node.cpu().unwrap(), Cpu::from_nanocores(196382978));
+        assert_eq!(node.memory().unwrap(), Memory::from_bytes(1893208064));
+    }
+
+    #[test]
+    fn pod_metrics() {
+        let pods = summary().pod_metrics(time::Duration::from_secs(10));
+
+        // job-x7k2p has no usageNanoCores yet
+        assert_eq!(pods.len(), 1);
+        let web = &pods[0];
+        assert_eq!(web.metadata.namespace.as_deref(), Some("default"));
+        assert_eq!(
+            web.metadata.uid.as_deref(),
+            Some("4a1a1b5e-8f2b-4a5a-9b8b-0f5cfe7b3a11")
+        );
+        assert_eq!(web.timestamp.0.to_string(), "2022-10-19T13:33:01Z");
+        assert_eq!(web.cpu().unwrap(), Cpu::from_millicores(251));
+        assert_eq!(web.memory().unwrap(), Memory::from_mib(72));
+    }
+
+    #[test]
+    fn resource_scrape() {
+        let previous = ResourceScrape::from(&summary());
+
+        let cpu = |time: &str, usage_core_nano_seconds| CpuStats {
+            time: Some(metav1::Time(time.parse().unwrap())),
+            usage_nano_cores: None,
+            usage_core_nano_seconds: Some(usage_core_nano_seconds),
+        };
+        let mut summary = summary();
+        let containers = &mut summary.pods[0].containers;
+        containers[0].cpu = Some(cpu("2022-10-19T13:33:11Z", 125_500_000_000));
+        containers[1].cpu = Some(cpu("2022-10-19T13:33:12Z", 3_350_000_000));
+        let current = ResourceScrape::from(&summary);
+
+        let worker = &previous.pods[&PodKey {
+            namespace: "batch".to_string(),
+            name: "job-x7k2p".to_string(),
+        }]["worker"];
+        assert_eq!(worker.cpu_usage_seconds, 0.015);
+        assert_eq!(
+            worker.start_time.unwrap().to_string(),
+            "2022-10-19T13:33:01Z"
+        );
+
+        let pods = current.pod_metrics(&previous);
+        let web = pods
+            .iter()
+            .find(|pod| pod.metadata.name.as_deref() == Some("web-0"))
+            .unwrap();
+        assert_eq!(web.containers[0].cpu().unwrap(), Cpu::from_millicores(500));
+        assert_eq!(web.containers[1].cpu().unwrap(), Cpu::from_millicores(10));
+        assert_eq!(web.window, time::Duration::from_secs(10));
+    }
+}
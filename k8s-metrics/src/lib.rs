@@ -25,17 +25,49 @@ use k8s::apimachinery::pkg::api::resource;
 use k8s::apimachinery::pkg::apis::meta::v1 as metav1;
 use k8s::jiff::Timestamp;
 
+#[cfg(feature = "kube")]
+pub use client::{MetricsClient, MetricsError};
+#[cfg(feature = "kube")]
+pub use discovery::MetricsApis;
 pub use metrics::v1beta1;
-pub use quantity::{QuantityExt, QuantityParseError};
+pub use quantity::{
+    Format, HumanFormat, IntoQuantity, ParsedQuantity, QuantityExt, QuantityParseError, UnitSystem,
+};
+pub use request::ResponseError;
+pub use selector::Selector;
+pub use units::{Cpu, Memory};
 
+#[cfg(feature = "kube")]
+pub mod client;
 pub mod custom_metrics;
+#[cfg(feature = "kube")]
+pub mod discovery;
 pub mod external_metrics;
+pub mod history;
+pub mod kubelet;
 pub mod metrics;
+#[cfg(feature = "poller")]
+pub mod poller;
 pub mod quantity;
+pub mod recording;
+mod request;
+pub mod selector;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod top;
+pub mod units;
 
 pub const METRICS_API_GROUP: &str = "metrics.k8s.io";
 pub const METRICS_API_VERSION: &str = "v1beta1";
+pub const CUSTOM_METRICS_API_GROUP: &str = "custom.metrics.k8s.io";
+pub const CUSTOM_METRICS_API_VERSION: &str = "v1beta2";
+pub const EXTERNAL_METRICS_API_GROUP: &str = "external.metrics.k8s.io";
+pub const EXTERNAL_METRICS_API_VERSION: &str = "v1beta1";
 
 fn default<T: Default>() -> T {
     T::default()
 }
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
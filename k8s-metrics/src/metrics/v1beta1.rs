@@ -2,16 +2,12 @@ use super::*;
 
 pub use node::NodeMetrics;
 pub use pod::PodMetrics;
+pub use usage::Usage;
 
 mod duration;
 mod node;
 mod pod;
-
-#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
-pub struct Usage {
-    pub cpu: resource::Quantity,
-    pub memory: resource::Quantity,
-}
+mod usage;
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Container {
@@ -19,22 +15,12 @@ pub struct Container {
     pub usage: Usage,
 }
 
-impl Usage {
-    pub fn cpu(&self) -> Result<f64, QuantityParseError> {
-        self.cpu.to_f64()
-    }
-
-    pub fn memory(&self) -> Result<i64, QuantityParseError> {
-        self.memory.to_memory()
-    }
-}
-
 impl Container {
-    pub fn cpu(&self) -> Result<f64, QuantityParseError> {
+    pub fn cpu(&self) -> Result<Cpu, QuantityParseError> {
         self.usage.cpu()
     }
 
-    pub fn memory(&self) -> Result<i64, QuantityParseError> {
+    pub fn memory(&self) -> Result<Memory, QuantityParseError> {
         self.usage.memory()
     }
 }
@@ -12,11 +12,11 @@ pub struct NodeMetrics {
 }
 
 impl NodeMetrics {
-    pub fn cpu(&self) -> Result<f64, QuantityParseError> {
+    pub fn cpu(&self) -> Result<Cpu, QuantityParseError> {
         self.usage.cpu()
     }
 
-    pub fn memory(&self) -> Result<i64, QuantityParseError> {
+    pub fn memory(&self) -> Result<Memory, QuantityParseError> {
         self.usage.memory()
     }
 }
@@ -91,7 +91,7 @@ mod tests {
         let node: NodeMetrics = json::from_str(NODE).unwrap();
         assert_eq!(node.metadata.name.as_deref(), Some("docker-desktop"));
         assert_eq!(node.window, time::Duration::from_secs_f64(23.5));
-        assert_eq!(node.usage.cpu().unwrap(), 0.196382978);
-        assert_eq!(node.usage.memory().unwrap(), 1893208064);
+        assert_eq!(node.usage.cpu().unwrap().cores(), 0.196382978);
+        assert_eq!(node.usage.memory().unwrap().bytes(), 1893208064);
     }
 }
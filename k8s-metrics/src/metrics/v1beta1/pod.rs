@@ -12,14 +12,23 @@ pub struct PodMetrics {
 }
 
 impl PodMetrics {
-    pub fn cpu(&self) -> Result<f64, QuantityParseError> {
+    /// Total usage of all containers
+    ///
+    pub fn usage(&self) -> Result<Usage, QuantityParseError> {
+        self.containers
+            .iter()
+            .map(|container| &container.usage)
+            .sum()
+    }
+
+    pub fn cpu(&self) -> Result<Cpu, QuantityParseError> {
         self.containers
             .iter()
             .map(|container| container.cpu())
             .sum()
     }
 
-    pub fn memory(&self) -> Result<i64, QuantityParseError> {
+    pub fn memory(&self) -> Result<Memory, QuantityParseError> {
         self.containers
             .iter()
             .map(|container| container.memory())
@@ -103,7 +112,8 @@ mod tests {
         assert_eq!(pod.containers.len(), 1);
         let container = &pod.containers[0];
         assert_eq!(container.name, "metrics-server");
-        assert_eq!(container.usage.cpu().unwrap(), 0.006082165);
-        assert_eq!(container.usage.memory().unwrap(), 22806528);
+        assert_eq!(container.usage.cpu().unwrap().cores(), 0.006082165);
+        assert_eq!(container.usage.memory().unwrap().bytes(), 22806528);
+        assert_eq!(pod.usage().unwrap(), container.usage);
     }
 }
@@ -5,22 +5,22 @@ use k8s::serde_json as json;
 #[test]
 fn nano() {
     let usage = json::from_str::<Usage>(r#"{"cpu":"1234567n","memory":"123Mi"}"#).unwrap();
-    assert_eq!(usage.cpu().unwrap(), 0.001234567_f64);
-    assert_eq!(usage.memory().unwrap(), 123 * 1024 * 1024);
+    assert_eq!(usage.cpu().unwrap().cores(), 0.001234567_f64);
+    assert_eq!(usage.memory().unwrap().bytes(), 123 * 1024 * 1024);
 }
 
 #[test]
 fn milli() {
     let usage = json::from_str::<Usage>(r#"{"cpu":"1234567m","memory":"8748Ki"}"#).unwrap();
-    assert_eq!(usage.cpu().unwrap(), 1234.567_f64);
-    assert_eq!(usage.memory().unwrap(), 8748 * 1024);
+    assert_eq!(usage.cpu().unwrap().cores(), 1234.567_f64);
+    assert_eq!(usage.memory().unwrap().bytes(), 8748 * 1024);
 }
 
 #[test]
 fn zero() {
     let usage = json::from_str::<Usage>(r#"{"cpu":"0","memory":"51428Ki"}"#).unwrap();
-    assert_eq!(usage.cpu().unwrap(), 0_f64);
-    assert_eq!(usage.memory().unwrap(), 51428 * 1024);
+    assert_eq!(usage.cpu().unwrap().cores(), 0_f64);
+    assert_eq!(usage.memory().unwrap().bytes(), 51428 * 1024);
 }
 
 #[test]
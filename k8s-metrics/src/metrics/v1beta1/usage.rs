@@ -0,0 +1,259 @@
+use std::cmp;
+use std::iter;
+use std::ops;
+
+use super::*;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub cpu: resource::Quantity,
+    pub memory: resource::Quantity,
+}
+
+impl Usage {
+    /// Create new `Usage` from exact `cpu` and `memory` values
+    ///
+    pub fn new(cpu: &ParsedQuantity, memory: &ParsedQuantity) -> Self {
+        let cpu = cpu.into();
+        let memory = memory.into();
+        Self { cpu, memory }
+    }
+
+    /// Zero CPU and memory usage
+    ///
+    pub fn zero() -> Self {
+        let zero = ParsedQuantity::default();
+        Self::new(&zero, &zero)
+    }
+
+    pub fn cpu(&self) -> Result<Cpu, QuantityParseError> {
+        Cpu::try_from(&self.cpu)
+    }
+
+    pub fn memory(&self) -> Result<Memory, QuantityParseError> {
+        Memory::try_from(&self.memory)
+    }
+
+    /// Sum of `self` and `other`, in canonical form
+    ///
+    pub fn checked_add(&self, other: &Self) -> Result<Self, QuantityParseError> {
+        let (cpu, memory) = self.parsed()?;
+        let (other_cpu, other_memory) = other.parsed()?;
+        Ok(Self::new(&(cpu + other_cpu), &(memory + other_memory)))
+    }
+
+    /// Difference between `self` and `other`, in canonical form
+    ///
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, QuantityParseError> {
+        let (cpu, memory) = self.parsed()?;
+        let (other_cpu, other_memory) = other.parsed()?;
+        Ok(Self::new(&(cpu - other_cpu), &(memory - other_memory)))
+    }
+
+    /// Both CPU and memory multiplied by `factor`, in canonical form
+    ///
+    pub fn scale(&self, factor: f64) -> Result<Self, QuantityParseError> {
+        let (cpu, memory) = self.parsed()?;
+        let scale = |quantity: ParsedQuantity| {
+            quantity
+                .mul_f64(factor)
+                .ok_or_else(|| QuantityParseError::new(&factor.to_string()))
+        };
+        Ok(Self::new(&scale(cpu)?, &scale(memory)?))
+    }
+
+    /// Compare CPU usage by value, `None` if either one can't be parsed
+    ///
+    pub fn cmp_cpu(&self, other: &Self) -> Option<cmp::Ordering> {
+        let cpu = self.cpu.to_parsed().ok()?;
+        let other = other.cpu.to_parsed().ok()?;
+        Some(cpu.cmp(&other))
+    }
+
+    /// Compare memory usage by value, `None` if either one can't be parsed
+    ///
+    pub fn cmp_memory(&self, other: &Self) -> Option<cmp::Ordering> {
+        let memory = self.memory.to_parsed().ok()?;
+        let other = other.memory.to_parsed().ok()?;
+        Some(memory.cmp(&other))
+    }
+
+    /// Compare both CPU and memory usage by value
+    ///
+    /// `self` is less (greater) than `other` when neither resource is greater (less)
+    /// and at least one is less (greater). Returns `None` when usages are not comparable,
+    /// i.e. one uses more CPU and the other more memory, or when either can't be parsed.
+    ///
+    pub fn compare(&self, other: &Self) -> Option<cmp::Ordering> {
+        let cpu = self.cmp_cpu(other)?;
+        let memory = self.cmp_memory(other)?;
+        match (cpu, memory) {
+            (cpu, memory) if cpu == memory => Some(cpu),
+            (cmp::Ordering::Equal, other) | (other, cmp::Ordering::Equal) => Some(other),
+            _ => None,
+        }
+    }
+
+    fn parsed(&self) -> Result<(ParsedQuantity, ParsedQuantity), QuantityParseError> {
+        Ok((self.cpu.to_parsed()?, self.memory.to_parsed()?))
+    }
+}
+
+impl ops::Add<&Usage> for &Usage {
+    type Output = Result<Usage, QuantityParseError>;
+
+    fn add(self, other: &Usage) -> Self::Output {
+        self.checked_add(other)
+    }
+}
+
+impl ops::Add for Usage {
+    type Output = Result<Self, QuantityParseError>;
+
+    fn add(self, other: Self) -> Self::Output {
+        self.checked_add(&other)
+    }
+}
+
+impl ops::Sub<&Usage> for &Usage {
+    type Output = Result<Usage, QuantityParseError>;
+
+    fn sub(self, other: &Usage) -> Self::Output {
+        self.checked_sub(other)
+    }
+}
+
+impl ops::Sub for Usage {
+    type Output = Result<Self, QuantityParseError>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self.checked_sub(&other)
+    }
+}
+
+impl ops::Mul<f64> for &Usage {
+    type Output = Result<Usage, QuantityParseError>;
+
+    fn mul(self, factor: f64) -> Self::Output {
+        self.scale(factor)
+    }
+}
+
+impl ops::Mul<f64> for Usage {
+    type Output = Result<Self, QuantityParseError>;
+
+    fn mul(self, factor: f64) -> Self::Output {
+        self.scale(factor)
+    }
+}
+
+impl<'a> iter::Sum<&'a Usage> for Result<Usage, QuantityParseError> {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Self {
+        total(iter.map(Usage::parsed))
+    }
+}
+
+impl iter::Sum<Usage> for Result<Usage, QuantityParseError> {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Self {
+        total(iter.map(|usage| usage.parsed()))
+    }
+}
+
+fn total<I>(iter: I) -> Result<Usage, QuantityParseError>
+where
+    I: Iterator<Item = Result<(ParsedQuantity, ParsedQuantity), QuantityParseError>>,
+{
+    let mut cpu = ParsedQuantity::default();
+    let mut memory = ParsedQuantity::default();
+    for usage in iter {
+        let (other_cpu, other_memory) = usage?;
+        cpu += &other_cpu;
+        memory += &other_memory;
+    }
+    Ok(Usage::new(&cpu, &memory))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(cpu: &str, memory: &str) -> Usage {
+        let cpu = resource::Quantity(cpu.to_string());
+        let memory = resource::Quantity(memory.to_string());
+        Usage { cpu, memory }
+    }
+
+    #[test]
+    fn add() {
+        let sum = (usage("250m", "64Mi") + usage("1", "64Mi")).unwrap();
+        assert_eq!(sum, usage("1250m", "128Mi"));
+    }
+
+    #[test]
+    fn sub() {
+        let diff = (&usage("6082165n", "22272Ki") - &usage("82165n", "272Ki")).unwrap();
+        assert_eq!(diff, usage("6m", "22000Ki"));
+    }
+
+    #[test]
+    fn add_invalid() {
+        let err = (usage("250m", "64Mi") + usage("1", "64Mb")).unwrap_err();
+        assert!(err.to_string().contains("64Mb"));
+    }
+
+    #[test]
+    fn sum() {
+        let usages = [
+            usage("100m", "1Gi"),
+            usage("200m", "512Mi"),
+            usage("300m", "512Mi"),
+        ];
+        let total = usages.iter().sum::<Result<Usage, _>>().unwrap();
+        assert_eq!(total, usage("600m", "2Gi"));
+    }
+
+    #[test]
+    fn sum_empty() {
+        let total = Vec::<Usage>::new()
+            .into_iter()
+            .sum::<Result<Usage, _>>()
+            .unwrap();
+        assert_eq!(total, Usage::zero());
+        assert_eq!(total, usage("0", "0"));
+    }
+
+    #[test]
+    fn scale() {
+        let half = (usage("1", "1Gi") * 0.5).unwrap();
+        assert_eq!(half, usage("500m", "512Mi"));
+    }
+
+    #[test]
+    fn compare() {
+        let small = usage("100m", "1Mi");
+        let large = usage("1", "1Gi");
+        let mixed = usage("2", "1Ki");
+
+        assert_eq!(small.cmp_cpu(&large), Some(cmp::Ordering::Less));
+        assert_eq!(large.cmp_memory(&small), Some(cmp::Ordering::Greater));
+        assert_eq!(small.compare(&large), Some(cmp::Ordering::Less));
+        assert_eq!(large.compare(&small), Some(cmp::Ordering::Greater));
+        assert_eq!(large.compare(&mixed), None);
+        assert_eq!(
+            usage("1000m", "1Mi").compare(&usage("1", "1024Ki")),
+            Some(cmp::Ordering::Equal)
+        );
+        assert_eq!(
+            usage("1", "2Mi").compare(&usage("1", "1Mi")),
+            Some(cmp::Ordering::Greater)
+        );
+    }
+
+    #[test]
+    fn sort_by_cpu() {
+        let mut usages = [usage("1", "1Mi"), usage("10m", "1Mi"), usage("200m", "1Mi")];
+        usages.sort_by(|a, b| a.cmp_cpu(b).unwrap_or(cmp::Ordering::Equal));
+        let cpu = usages.map(|usage| usage.cpu.0);
+        assert_eq!(cpu, ["10m", "200m", "1"]);
+    }
+}
@@ -0,0 +1,354 @@
+//! Watch-like stream of metrics, polled from `metrics.k8s.io` API
+//!
+//! Metrics API does not support `watch`, so the poller lists metrics every interval
+//! and compares them with the previous list. Objects whose `timestamp` did not advance
+//! are not reported again.
+//!
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasher as _, Hasher as _};
+
+use futures::stream::{self, Stream};
+use kube::api;
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+use super::*;
+
+/// Metrics object polled by `Poller`
+///
+pub trait MetricsObject:
+    Clone + fmt::Debug + serde::de::DeserializeOwned + kube::Resource + Send + Sync + 'static
+{
+    /// Time the metrics were collected at
+    ///
+    fn timestamp(&self) -> Timestamp;
+}
+
+impl MetricsObject for PodMetrics {
+    fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+}
+
+impl MetricsObject for NodeMetrics {
+    fn timestamp(&self) -> Timestamp {
+        self.timestamp.0
+    }
+}
+
+/// Change of a polled metrics object
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<K> {
+    /// Metrics of the object are reported for the first time
+    ///
+    Added(K),
+    /// Newer metrics of an already reported object
+    ///
+    Updated(K),
+    /// Metrics of the object are no longer reported, carries the last ones seen
+    ///
+    Removed(K),
+}
+
+impl<K> Event<K> {
+    pub fn object(&self) -> &K {
+        match self {
+            Self::Added(object) | Self::Updated(object) | Self::Removed(object) => object,
+        }
+    }
+
+    pub fn into_object(self) -> K {
+        match self {
+            Self::Added(object) | Self::Updated(object) | Self::Removed(object) => object,
+        }
+    }
+}
+
+/// Polls metrics at a fixed interval, see `Poller::stream()`
+///
+#[derive(Clone, Debug)]
+pub struct Poller<K> {
+    api: api::Api<K>,
+    selector: Option<String>,
+    interval: time::Duration,
+    min_backoff: time::Duration,
+    max_backoff: time::Duration,
+}
+
+impl<K: MetricsObject> Poller<K> {
+    pub fn new(api: api::Api<K>) -> Self {
+        Self {
+            api,
+            selector: None,
+            interval: time::Duration::from_secs(15),
+            min_backoff: time::Duration::from_secs(1),
+            max_backoff: time::Duration::from_secs(60),
+        }
+    }
+
+    /// Time between two polls, 15 seconds by default, the resolution of metrics-server
+    ///
+    pub fn interval(self, interval: time::Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Poll only objects matching label `selector`
+    ///
+    pub fn selector(self, selector: &str) -> Self {
+        let selector = Some(selector.to_string());
+        Self { selector, ..self }
+    }
+
+    /// Bounds of the delay before retrying a failed poll, 1 second and 1 minute by default
+    ///
+    /// The delay doubles with every consecutive failure and is jittered down by up to a half.
+    ///
+    pub fn backoff(self, min_backoff: time::Duration, max_backoff: time::Duration) -> Self {
+        Self {
+            min_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Stream of events, starting with `Event::Added` for every object of the first poll
+    ///
+    /// Failed polls are reported as errors and retried after a backoff, the stream never ends.
+    ///
+    pub fn stream(self) -> impl Stream<Item = Result<Event<K>, MetricsError>> + Send {
+        let state = State {
+            poller: self,
+            objects: default(),
+            events: default(),
+            delay: None,
+            backoff: None,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.events.pop_front() {
+                    return Some((Ok(event), state));
+                }
+                if let Some(delay) = state.delay.take() {
+                    tokio::time::sleep(delay).await;
+                }
+                let poller = &state.poller;
+                let objects = client::list(poller.api.clone(), poller.selector.as_deref()).await;
+                match objects {
+                    Ok(objects) => {
+                        state.update(objects);
+                        state.backoff = None;
+                        state.delay = Some(state.poller.interval);
+                    }
+                    Err(err) => {
+                        state.delay = Some(state.next_backoff());
+                        return Some((Err(err), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl MetricsClient {
+    /// Poll metrics of pods in `namespace`, or in all namespaces
+    ///
+    pub fn poll_pods(&self, namespace: Option<&str>) -> Poller<PodMetrics> {
+        let client = self.client().clone();
+        let api = match namespace {
+            Some(namespace) => api::Api::namespaced(client, namespace),
+            None => api::Api::all(client),
+        };
+        Poller::new(api)
+    }
+
+    /// Poll metrics of nodes
+    ///
+    pub fn poll_nodes(&self) -> Poller<NodeMetrics> {
+        Poller::new(api::Api::all(self.client().clone()))
+    }
+}
+
+struct State<K> {
+    poller: Poller<K>,
+    objects: BTreeMap<(String, String), K>,
+    events: VecDeque<Event<K>>,
+    delay: Option<time::Duration>,
+    backoff: Option<time::Duration>,
+}
+
+impl<K: MetricsObject> State<K> {
+    fn update(&mut self, objects: Vec<K>) {
+        let mut previous = std::mem::take(&mut self.objects);
+        for object in objects {
+            let meta = object.meta();
+            let key = (
+                meta.namespace.clone().unwrap_or_default(),
+                meta.name.clone().unwrap_or_default(),
+            );
+            let event = match previous.remove(&key) {
+                None => Some(Event::Added(object.clone())),
+                Some(seen) if seen.timestamp() < object.timestamp() => {
+                    Some(Event::Updated(object.clone()))
+                }
+                Some(_) => None,
+            };
+            self.events.extend(event);
+            self.objects.insert(key, object);
+        }
+        self.events
+            .extend(previous.into_values().map(Event::Removed));
+    }
+
+    fn next_backoff(&mut self) -> time::Duration {
+        let poller = &self.poller;
+        let backoff = self
+            .backoff
+            .map_or(poller.min_backoff, |backoff| backoff.saturating_mul(2))
+            .min(poller.max_backoff);
+        self.backoff = Some(backoff);
+        jitter(backoff)
+    }
+}
+
+/// Random delay between a half and the whole of `delay`
+///
+fn jitter(delay: time::Duration) -> time::Duration {
+    let random = RandomState::new().build_hasher().finish();
+    delay.mul_f64(0.5 + random as f64 / u64::MAX as f64 / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt as _;
+    use kube::client::Body;
+    use tower_test::mock;
+
+    use super::*;
+
+    const NOT_READY: &str = r#"{
+  "kind": "Status",
+  "apiVersion": "v1",
+  "metadata": {},
+  "status": "Failure",
+  "message": "the server is currently unable to handle the request",
+  "reason": "ServiceUnavailable",
+  "code": 503
+}"#;
+
+    fn pods(pods: &[(&str, &str)]) -> String {
+        let items = pods
+            .iter()
+            .map(|(name, timestamp)| {
+                format!(
+                    r#"{{"metadata":{{"name":"{name}","namespace":"default"}},"timestamp":"{timestamp}","window":"15s","containers":[]}}"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"kind":"PodMetricsList","apiVersion":"metrics.k8s.io/v1beta1","metadata":{{}},"items":[{items}]}}"#
+        )
+    }
+
+    /// Mock client answering consecutive requests with `responses`
+    ///
+    fn mock_client(responses: Vec<(u16, String)>) -> MetricsClient {
+        let (service, mut handle) = mock::pair::<http::Request<Body>, http::Response<Body>>();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (request, send) = handle.next_request().await.expect("request expected");
+                assert_eq!(
+                    request.uri().path_and_query().unwrap(),
+                    "/apis/metrics.k8s.io/v1beta1/namespaces/default/pods?&labelSelector=app%3Dweb"
+                );
+                let response = http::Response::builder()
+                    .status(status)
+                    .body(Body::from(body.into_bytes()))
+                    .unwrap();
+                send.send_response(response);
+            }
+        });
+        MetricsClient::new(kube::Client::new(service, "default"))
+    }
+
+    fn name(event: &Event<PodMetrics>) -> String {
+        let kind = match event {
+            Event::Added(_) => "added",
+            Event::Updated(_) => "updated",
+            Event::Removed(_) => "removed",
+        };
+        let name = event.object().metadata.name.as_deref().unwrap_or_default();
+        format!("{kind} {name}")
+    }
+
+    #[tokio::test]
+    async fn events() {
+        let client = mock_client(vec![
+            (
+                200,
+                pods(&[
+                    ("web-0", "2022-10-09T11:51:20Z"),
+                    ("web-1", "2022-10-09T11:51:20Z"),
+                ]),
+            ),
+            (
+                200,
+                pods(&[
+                    ("web-0", "2022-10-09T11:51:20Z"),
+                    ("web-1", "2022-10-09T11:51:35Z"),
+                ]),
+            ),
+            (503, NOT_READY.to_string()),
+            (200, pods(&[("web-0", "2022-10-09T11:51:50Z")])),
+        ]);
+        let stream = client
+            .poll_pods(Some("default"))
+            .selector("app=web")
+            .interval(time::Duration::from_millis(1))
+            .backoff(
+                time::Duration::from_millis(1),
+                time::Duration::from_millis(2),
+            )
+            .stream();
+
+        let events = stream
+            .take(6)
+            .map(|event| event.map(|event| name(&event)))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events[0].as_deref().unwrap(), "added web-0");
+        assert_eq!(events[1].as_deref().unwrap(), "added web-1");
+        assert_eq!(events[2].as_deref().unwrap(), "updated web-1");
+        assert!(
+            matches!(events[3], Err(MetricsError::NotReady(_))),
+            "{:?}",
+            events[3]
+        );
+        assert_eq!(events[4].as_deref().unwrap(), "updated web-0");
+        assert_eq!(events[5].as_deref().unwrap(), "removed web-1");
+    }
+
+    #[tokio::test]
+    async fn backoff() {
+        let (service, _handle) = mock::pair::<http::Request<Body>, http::Response<Body>>();
+        let client = kube::Client::new(service, "default");
+        let poller = Poller::<NodeMetrics>::new(api::Api::all(client))
+            .backoff(time::Duration::from_secs(1), time::Duration::from_secs(3));
+        let mut state = State {
+            poller,
+            objects: default(),
+            events: default(),
+            delay: None,
+            backoff: None,
+        };
+
+        let bounds = [(500, 1000), (1000, 2000), (1500, 3000), (1500, 3000)];
+        for (min, max) in bounds {
+            let delay = state.next_backoff().as_millis();
+            assert!((min..=max).contains(&delay), "{delay} not in {min}..={max}");
+        }
+    }
+}
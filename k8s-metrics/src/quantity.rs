@@ -1,36 +1,116 @@
 use super::*;
 
+pub use human::{HumanFormat, UnitSystem};
+pub use parsed::{Format, ParsedQuantity};
+
+mod human;
+mod parsed;
+mod parser;
+
 pub trait QuantityExt {
+    /// Value of this quantity as an integer, rounded up (e.g. memory in bytes)
+    ///
     fn to_memory(&self) -> Result<i64, QuantityParseError>;
+
+    /// Value of this quantity as a floating point number (e.g. CPU in cores)
+    ///
     fn to_f64(&self) -> Result<f64, QuantityParseError>;
+
+    /// Exact value of this quantity
+    ///
+    fn to_parsed(&self) -> Result<ParsedQuantity, QuantityParseError>;
+
+    /// CPU formatted like `kubectl top` does, in millicores, e.g. `250m`
+    ///
+    fn to_cpu_string(&self) -> Result<String, QuantityParseError>;
+
+    /// Memory formatted like `kubectl top` does, in whole mebibytes, e.g. `128Mi`
+    ///
+    fn to_memory_string(&self) -> Result<String, QuantityParseError>;
+
+    /// Human readable form with configurable precision and units
+    ///
+    fn humanize(&self, format: HumanFormat) -> Result<String, QuantityParseError>;
 }
 
 impl QuantityExt for resource::Quantity {
     fn to_memory(&self) -> Result<i64, QuantityParseError> {
-        if let Some((number, _unit)) = self.0.split_once("Ki") {
-            number.parse::<i64>().map(|n| n * 1024)
-        } else if let Some((number, _unit)) = self.0.split_once("Mi") {
-            number.parse::<i64>().map(|n| n * 1024 * 1024)
-        } else if let Some((number, _unit)) = self.0.split_once("Gi") {
-            number.parse::<i64>().map(|n| n * 1024 * 1024 * 1024)
-        } else {
-            self.0.parse::<i64>()
-        }
-        .map_err(|_e| QuantityParseError::new(&self.0))
+        self.to_parsed()?
+            .to_scaled_i64(0)
+            .ok_or_else(|| QuantityParseError::new(&self.0))
     }
 
     fn to_f64(&self) -> Result<f64, QuantityParseError> {
-        let out = if let Some((number, _unit)) = self.0.split_once('n') {
-            number.parse::<f64>().map(|f| f / 1_000_000_000_f64)
-        } else if let Some((number, _unit)) = self.0.split_once('u') {
-            number.parse::<f64>().map(|f| f / 1_000_000_f64)
-        } else if let Some((number, _unit)) = self.0.split_once('m') {
-            number.parse::<f64>().map(|f| f / 1_000_f64)
-        } else {
-            self.0.parse::<f64>()
-        };
-
-        out.map_err(|_e| QuantityParseError::new(&self.0))
+        self.to_parsed().map(|quantity| quantity.to_f64())
+    }
+
+    fn to_parsed(&self) -> Result<ParsedQuantity, QuantityParseError> {
+        ParsedQuantity::try_from(self)
+    }
+
+    fn to_cpu_string(&self) -> Result<String, QuantityParseError> {
+        Cpu::try_from(self).map(|cpu| cpu.to_string())
+    }
+
+    fn to_memory_string(&self) -> Result<String, QuantityParseError> {
+        Memory::try_from(self).map(|memory| memory.to_string())
+    }
+
+    fn humanize(&self, format: HumanFormat) -> Result<String, QuantityParseError> {
+        self.to_parsed().map(|quantity| format.format(&quantity))
+    }
+}
+
+/// Values that can be used as a metric value
+///
+/// Floating point values are kept with nano precision, more precise values are rounded up
+/// (away from zero) like any other quantity.
+///
+pub trait IntoQuantity {
+    fn into_quantity(self) -> resource::Quantity;
+}
+
+impl IntoQuantity for resource::Quantity {
+    fn into_quantity(self) -> resource::Quantity {
+        self
+    }
+}
+
+impl IntoQuantity for ParsedQuantity {
+    fn into_quantity(self) -> resource::Quantity {
+        self.into()
+    }
+}
+
+impl IntoQuantity for i64 {
+    fn into_quantity(self) -> resource::Quantity {
+        ParsedQuantity::from(self).into()
+    }
+}
+
+/// # Panics
+///
+/// Panics if the value is not finite.
+///
+impl IntoQuantity for f64 {
+    fn into_quantity(self) -> resource::Quantity {
+        format!("{self:e}")
+            .parse::<ParsedQuantity>()
+            .expect("metric value is finite")
+            .with_format(Format::DecimalSI)
+            .into()
+    }
+}
+
+impl IntoQuantity for Cpu {
+    fn into_quantity(self) -> resource::Quantity {
+        self.into()
+    }
+}
+
+impl IntoQuantity for Memory {
+    fn into_quantity(self) -> resource::Quantity {
+        self.into()
     }
 }
 
@@ -39,7 +119,7 @@ impl QuantityExt for resource::Quantity {
 pub struct QuantityParseError(String);
 
 impl QuantityParseError {
-    fn new(text: &str) -> Self {
+    pub(crate) fn new(text: &str) -> Self {
         Self(text.to_string())
     }
 }
@@ -92,6 +172,97 @@ mod tests {
         assert_eq!(q, 3.491);
     }
 
+    #[test]
+    fn memory_fractional() {
+        let q = quantity("1.5Gi").to_memory().unwrap();
+        assert_eq!(q, 1610612736);
+    }
+
+    #[test]
+    fn memory_Ti_Pi_Ei() {
+        assert_eq!(quantity("2Ti").to_memory().unwrap(), 2 << 40);
+        assert_eq!(quantity("3Pi").to_memory().unwrap(), 3 << 50);
+        assert_eq!(quantity("1Ei").to_memory().unwrap(), 1 << 60);
+    }
+
+    #[test]
+    fn memory_decimal_si() {
+        assert_eq!(quantity("500M").to_memory().unwrap(), 500_000_000);
+        assert_eq!(quantity("1k").to_memory().unwrap(), 1000);
+        assert_eq!(quantity("2G").to_memory().unwrap(), 2_000_000_000);
+    }
+
+    #[test]
+    fn memory_exponent() {
+        assert_eq!(quantity("1e3").to_memory().unwrap(), 1000);
+        assert_eq!(
+            quantity("9E").to_memory().unwrap(),
+            9_000_000_000_000_000_000
+        );
+        quantity("12E").to_memory().unwrap_err();
+    }
+
+    #[test]
+    fn memory_rounds_up() {
+        assert_eq!(quantity("100m").to_memory().unwrap(), 1);
+        assert_eq!(quantity("1.0001k").to_memory().unwrap(), 1001);
+    }
+
+    #[test]
+    fn memory_negative() {
+        assert_eq!(quantity("-1.5Ki").to_memory().unwrap(), -1536);
+    }
+
+    #[test]
+    fn memory_overflow() {
+        quantity("16Ei").to_memory().unwrap_err();
+    }
+
+    #[test]
+    fn cpu_fractional() {
+        let q = quantity("1.5").to_f64().unwrap();
+        assert_eq!(q, 1.5);
+    }
+
+    #[test]
+    fn cpu_exponent() {
+        assert_eq!(quantity("25e-2").to_f64().unwrap(), 0.25);
+        assert_eq!(quantity("+1e3").to_f64().unwrap(), 1000.0);
+    }
+
+    #[test]
+    fn cpu_binary() {
+        let q = quantity("1Ki").to_f64().unwrap();
+        assert_eq!(q, 1024.0);
+    }
+
+    #[test]
+    fn invalid() {
+        quantity("1.5.Gi").to_f64().unwrap_err();
+        quantity("Mi").to_memory().unwrap_err();
+        quantity("1 Mi").to_memory().unwrap_err();
+    }
+
+    #[test]
+    fn into_quantity() {
+        assert_eq!(quantity("66m").into_quantity(), quantity("66m"));
+        assert_eq!(42_i64.into_quantity(), quantity("42"));
+        assert_eq!(0.5_f64.into_quantity(), quantity("500m"));
+        assert_eq!(1.23456_f64.into_quantity(), quantity("1234560u"));
+        assert_eq!(2.0_f64.into_quantity(), quantity("2"));
+        assert_eq!(0.0001_f64.into_quantity(), quantity("100u"));
+        assert_eq!(1e-10_f64.into_quantity(), quantity("1n"));
+        assert_eq!((-0.0005_f64).into_quantity(), quantity("-500u"));
+        assert_eq!(1e20_f64.into_quantity(), quantity("100E"));
+        assert_eq!(Memory::from_mib(64).into_quantity(), quantity("64Mi"));
+    }
+
+    #[test]
+    #[should_panic(expected = "metric value is finite")]
+    fn into_quantity_nan() {
+        f64::NAN.into_quantity();
+    }
+
     fn quantity(v: &str) -> resource::Quantity {
         resource::Quantity(v.to_string())
     }
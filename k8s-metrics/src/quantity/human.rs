@@ -0,0 +1,230 @@
+use num_bigint::BigInt;
+use num_traits::Signed as _;
+
+use super::*;
+
+const BINARY_UNITS: [&str; 7] = ["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+const DECIMAL_UNITS: [&str; 10] = ["n", "u", "m", "", "k", "M", "G", "T", "P", "E"];
+
+/// Unit system used for human readable formatting
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnitSystem {
+    /// Powers of 1024 - `Ki`, `Mi`, `Gi`, ...
+    ///
+    #[default]
+    Binary,
+    /// Powers of 1000 - `k`, `M`, `G`, ... and `m`, `u`, `n` for values below one
+    ///
+    Decimal,
+}
+
+/// Human readable formatting of quantities
+///
+/// Picks the largest unit the value is at least one of, and prints the value
+/// in that unit with given number of fractional digits, e.g. `1.5Gi` or `250m`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HumanFormat {
+    units: UnitSystem,
+    precision: usize,
+}
+
+impl HumanFormat {
+    /// Binary units (`Ki`, `Mi`, `Gi`, ...), usually used for memory
+    ///
+    pub fn binary() -> Self {
+        Self {
+            units: UnitSystem::Binary,
+            precision: 0,
+        }
+    }
+
+    /// Decimal units (`m`, `k`, `M`, ...), usually used for CPU
+    ///
+    pub fn decimal() -> Self {
+        Self {
+            units: UnitSystem::Decimal,
+            precision: 0,
+        }
+    }
+
+    /// Set number of fractional digits
+    ///
+    pub fn precision(self, precision: usize) -> Self {
+        Self { precision, ..self }
+    }
+
+    /// Set unit system
+    ///
+    pub fn units(self, units: UnitSystem) -> Self {
+        Self { units, ..self }
+    }
+
+    /// Format `quantity` according to these options
+    ///
+    pub fn format(&self, quantity: &ParsedQuantity) -> String {
+        let value = quantity.to_f64();
+        if !value.is_finite() {
+            return self.format_exact(quantity);
+        }
+        let (value, unit) = match self.units {
+            UnitSystem::Binary => scale(value, 1024.0, &BINARY_UNITS, 0, self.precision),
+            UnitSystem::Decimal => scale(value, 1000.0, &DECIMAL_UNITS, 3, self.precision),
+        };
+        format!("{value:.precision$}{unit}", precision = self.precision)
+    }
+
+    /// Format `quantity` too large for `f64` exactly, in the largest unit
+    ///
+    /// Falls back to the canonical form when the precision is beyond any quantity.
+    ///
+    fn format_exact(&self, quantity: &ParsedQuantity) -> String {
+        let (base, unit) = match self.units {
+            UnitSystem::Binary => (BigInt::from(1) << 60, BINARY_UNITS[BINARY_UNITS.len() - 1]),
+            UnitSystem::Decimal => (
+                BigInt::from(10).pow(18),
+                DECIMAL_UNITS[DECIMAL_UNITS.len() - 1],
+            ),
+        };
+        let value = i32::try_from(self.precision)
+            .ok()
+            .and_then(|precision| quantity.to_scaled_bigint(-precision));
+        let Some(value) = value else {
+            return quantity.to_string();
+        };
+
+        // Rounded half away from zero
+        let value: BigInt = (&value * 2 + value.signum() * &base) / (base * 2);
+        let sign = if value.is_negative() { "-" } else { "" };
+        let digits = format!("{:0>width$}", value.magnitude(), width = self.precision + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.precision);
+        if fraction.is_empty() {
+            format!("{sign}{integer}{unit}")
+        } else {
+            format!("{sign}{integer}.{fraction}{unit}")
+        }
+    }
+}
+
+/// `value` in the largest of `units` it is at least one of, `units[one]` being the unit of one
+///
+/// The unit is checked again after rounding to `precision`, so that e.g. 1048575 bytes
+/// are `1.0Mi` rather than `1024.0Ki`.
+///
+fn scale(
+    mut value: f64,
+    base: f64,
+    units: &[&'static str],
+    one: usize,
+    precision: usize,
+) -> (f64, &'static str) {
+    let mut unit = one;
+    while value.abs() >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    while value != 0.0 && value.abs() < 1.0 && unit > 0 {
+        value *= base;
+        unit -= 1;
+    }
+    if round(value, precision).abs() >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    (value, units[unit])
+}
+
+/// `value` rounded to `precision` fractional digits, exactly like it is printed
+///
+fn round(value: f64, precision: usize) -> f64 {
+    format!("{value:.precision$}").parse().unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantity(v: &str) -> resource::Quantity {
+        resource::Quantity(v.to_string())
+    }
+
+    #[test]
+    fn kubectl_top_cpu() {
+        assert_eq!(quantity("196382978n").to_cpu_string().unwrap(), "197m");
+        assert_eq!(quantity("0").to_cpu_string().unwrap(), "0m");
+        assert_eq!(quantity("2").to_cpu_string().unwrap(), "2000m");
+        quantity("2x").to_cpu_string().unwrap_err();
+    }
+
+    #[test]
+    fn kubectl_top_memory() {
+        assert_eq!(quantity("1848836Ki").to_memory_string().unwrap(), "1805Mi");
+        assert_eq!(quantity("22272Ki").to_memory_string().unwrap(), "21Mi");
+        assert_eq!(quantity("500Ki").to_memory_string().unwrap(), "0Mi");
+        assert_eq!(quantity("1Gi").to_memory_string().unwrap(), "1024Mi");
+    }
+
+    #[test]
+    fn binary() {
+        let format = HumanFormat::binary();
+        assert_eq!(quantity("1.5Gi").humanize(format).unwrap(), "2Gi");
+        assert_eq!(
+            quantity("1.5Gi").humanize(format.precision(1)).unwrap(),
+            "1.5Gi"
+        );
+        assert_eq!(quantity("1000").humanize(format).unwrap(), "1000");
+        assert_eq!(quantity("2048Ei").humanize(format).unwrap(), "2048Ei");
+
+        let huge = quantity("1e309").humanize(format.precision(1)).unwrap();
+        assert!(huge.starts_with("8673617379884035472"));
+        assert!(huge.ends_with(".0Ei"));
+        assert_eq!(huge.len(), 295);
+    }
+
+    #[test]
+    fn decimal() {
+        let format = HumanFormat::decimal().precision(2);
+        assert_eq!(quantity("1.5Gi").humanize(format).unwrap(), "1.61G");
+        assert_eq!(quantity("250m").humanize(format).unwrap(), "250.00m");
+        assert_eq!(quantity("257n").humanize(format).unwrap(), "257.00n");
+        assert_eq!(quantity("0").humanize(format).unwrap(), "0.00");
+        assert_eq!(quantity("-1500").humanize(format).unwrap(), "-1.50k");
+        assert_eq!(
+            quantity("1e1000").humanize(format).unwrap(),
+            format!("1{}.00E", "0".repeat(982))
+        );
+        assert_eq!(
+            quantity("-1.5e400")
+                .humanize(HumanFormat::decimal())
+                .unwrap(),
+            format!("-15{}E", "0".repeat(381))
+        );
+    }
+
+    #[test]
+    fn rounding_to_next_unit() {
+        let format = HumanFormat::binary().precision(1);
+        assert_eq!(quantity("1048575").humanize(format).unwrap(), "1.0Mi");
+        assert_eq!(quantity("1048524").humanize(format).unwrap(), "1023.9Ki");
+        assert_eq!(quantity("-1048575").humanize(format).unwrap(), "-1.0Mi");
+        assert_eq!(
+            quantity("1023.99Ei")
+                .humanize(HumanFormat::binary())
+                .unwrap(),
+            "1024Ei"
+        );
+
+        let format = HumanFormat::decimal();
+        assert_eq!(quantity("999999").humanize(format).unwrap(), "1M");
+        assert_eq!(quantity("999.6u").humanize(format).unwrap(), "1m");
+        assert_eq!(quantity("999.6m").humanize(format).unwrap(), "1");
+        assert_eq!(quantity("999.4m").humanize(format).unwrap(), "999m");
+    }
+
+    #[test]
+    fn units() {
+        let format = HumanFormat::binary().units(UnitSystem::Decimal);
+        assert_eq!(format, HumanFormat::decimal());
+    }
+}
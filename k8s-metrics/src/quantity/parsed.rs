@@ -0,0 +1,737 @@
+use std::cmp;
+use std::fmt;
+use std::iter;
+use std::ops;
+use std::str;
+
+use num_bigint::BigInt;
+use num_integer::Integer as _;
+use num_traits::{Signed as _, ToPrimitive as _, Zero as _};
+
+use super::*;
+
+/// Smallest scale a quantity is kept at, values with more precision are rounded up
+///
+const NANO: i32 = -9;
+
+/// Largest decimal exponent accepted when parsing, e.g. `1e1000`
+///
+/// Kubernetes caps quantities at 2^63 - 1 anyway, larger exponents would only make
+/// arithmetic on the exact value expensive.
+///
+const MAX_EXPONENT: u32 = 1000;
+
+/// Largest scale a quantity can have, the same as the largest exponent accepted when parsing
+///
+const MAX_SCALE: i32 = MAX_EXPONENT as i32;
+
+/// Largest power of ten a quantity is multiplied by when rescaled, the distance between
+/// the largest and the smallest scale
+///
+const MAX_RESCALE: i64 = MAX_SCALE as i64 - NANO as i64;
+
+const DECIMAL_SUFFIXES: [(i32, &str); 10] = [
+    (-9, "n"),
+    (-6, "u"),
+    (-3, "m"),
+    (0, ""),
+    (3, "k"),
+    (6, "M"),
+    (9, "G"),
+    (12, "T"),
+    (15, "P"),
+    (18, "E"),
+];
+
+const BINARY_SUFFIXES: [&str; 7] = ["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+
+/// The way a quantity is formatted when serialized back to string
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Binary SI suffixes, e.g. `12Mi` (12 * 2^20)
+    ///
+    BinarySI,
+    /// Decimal SI suffixes, e.g. `12M` (12 * 10^6)
+    ///
+    #[default]
+    DecimalSI,
+    /// Decimal exponent, e.g. `12e6` (12 * 10^6)
+    ///
+    DecimalExponent,
+}
+
+/// Exact, arbitrary precision value of a `resource::Quantity`
+///
+/// The value is `mantissa * 10^scale`. Like Go's `resource.Quantity` it remembers
+/// the `Format` it was parsed from and uses it to produce the canonical string form.
+/// Equality and ordering compare values only, regardless of format.
+///
+#[derive(Clone, Debug, Default)]
+pub struct ParsedQuantity {
+    mantissa: BigInt,
+    scale: i32,
+    format: Format,
+}
+
+impl ParsedQuantity {
+    /// Create new `ParsedQuantity` with value `mantissa * 10^scale`
+    ///
+    /// Values more precise than nano-units are rounded up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is larger than 1000, see [`ParsedQuantity::checked_new`].
+    ///
+    pub fn new(mantissa: impl Into<BigInt>, scale: i32, format: Format) -> Self {
+        Self::checked_new(mantissa, scale, format).expect("quantity scale out of range")
+    }
+
+    /// Create new `ParsedQuantity` with value `mantissa * 10^scale`, or `None` if `scale`
+    /// is larger than 1000
+    ///
+    /// Like the exponents accepted when parsing, the scale is bounded far beyond any
+    /// quantity Kubernetes accepts, which keeps arithmetic on the exact value cheap.
+    /// Values more precise than nano-units are rounded up.
+    ///
+    pub fn checked_new(mantissa: impl Into<BigInt>, scale: i32, format: Format) -> Option<Self> {
+        if scale > MAX_SCALE {
+            return None;
+        }
+        let quantity = Self {
+            mantissa: mantissa.into(),
+            scale,
+            format,
+        };
+        Some(quantity.round_to_nano())
+    }
+
+    /// Zero value in given `format`
+    ///
+    pub fn zero(format: Format) -> Self {
+        Self::new(0, 0, format)
+    }
+
+    /// Set format used for canonical string form
+    ///
+    pub fn with_format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
+    pub fn mantissa(&self) -> &BigInt {
+        &self.mantissa
+    }
+
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    /// Value as a floating point number, which may lose precision
+    ///
+    pub fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.mantissa, self.scale)
+            .parse()
+            .unwrap_or_default()
+    }
+
+    /// Value in units of `10^scale`, rounded up (away from zero)
+    ///
+    /// `to_scaled_i64(0)` is the integer value, `to_scaled_i64(-3)` is in milli-units, etc.
+    /// Returns `None` when the value does not fit `i64`.
+    ///
+    pub fn to_scaled_i64(&self, scale: i32) -> Option<i64> {
+        if self.is_zero() {
+            return Some(0);
+        }
+        let exponent = i64::from(self.scale) - i64::from(scale);
+        // At least 10^19 when not zero, beyond i64::MAX
+        if exponent > 18 {
+            return None;
+        }
+        self.to_scaled_bigint(scale)?.to_i64()
+    }
+
+    /// Value in units of `10^scale`, rounded up (away from zero)
+    ///
+    /// Returns `None` when `scale` is more than 1009 below the scale of the value, i.e.
+    /// further than the largest and the smallest scale of any quantity are apart.
+    ///
+    pub fn to_scaled_bigint(&self, scale: i32) -> Option<BigInt> {
+        if self.is_zero() {
+            return Some(BigInt::zero());
+        }
+        let exponent = i64::from(self.scale) - i64::from(scale);
+        if exponent > MAX_RESCALE {
+            None
+        } else if exponent >= 0 {
+            Some(&self.mantissa * pow10(exponent))
+        } else {
+            Some(div_pow10_round_up(&self.mantissa, -exponent))
+        }
+    }
+
+    /// Value multiplied by `factor`, or `None` if `factor` is not finite or the result
+    /// is out of range
+    ///
+    pub fn mul_f64(&self, factor: f64) -> Option<Self> {
+        let factor = format!("{factor:e}").parse::<Self>().ok()?;
+        let scale = self.scale.checked_add(factor.scale)?;
+        Self::checked_new(&self.mantissa * factor.mantissa, scale, self.format)
+    }
+
+    /// Canonical string form, split into number and suffix
+    ///
+    /// Follows the rules of Go's `resource.Quantity.CanonicalizeBytes`
+    ///
+    pub fn canonicalize(&self) -> (String, String) {
+        if self.is_zero() {
+            return ("0".to_string(), String::new());
+        }
+
+        let format = match self.format {
+            Format::BinarySI => match self.mantissa_at(0) {
+                Some(value) if value.abs() >= BigInt::from(1024) => Format::BinarySI,
+                // Avoid rounding, show small and fractional values as DecimalSI
+                _ => Format::DecimalSI,
+            },
+            format => format,
+        };
+
+        match format {
+            Format::BinarySI => {
+                let mut value = self
+                    .to_scaled_bigint(0)
+                    .expect("quantity scale is within range");
+                let mut exponent = 0;
+                let base = BigInt::from(1024);
+                while exponent < BINARY_SUFFIXES.len() - 1 && value.is_multiple_of(&base) {
+                    value /= &base;
+                    exponent += 1;
+                }
+                (value.to_string(), BINARY_SUFFIXES[exponent].to_string())
+            }
+            Format::DecimalSI | Format::DecimalExponent => {
+                let mut mantissa = self.mantissa.clone();
+                let mut exponent = i64::from(self.scale);
+                let ten = BigInt::from(10);
+                while mantissa.is_multiple_of(&ten) {
+                    mantissa /= &ten;
+                    exponent += 1;
+                }
+                while exponent.rem_euclid(3) != 0 {
+                    mantissa *= &ten;
+                    exponent -= 1;
+                }
+
+                let suffix = match format {
+                    Format::DecimalSI => decimal_suffix(exponent),
+                    _ => None,
+                }
+                .map_or_else(
+                    || match exponent {
+                        0 => String::new(),
+                        exponent => format!("e{exponent}"),
+                    },
+                    str::to_string,
+                );
+
+                (mantissa.to_string(), suffix)
+            }
+        }
+    }
+
+    /// Mantissa when the value is expressed exactly at given `scale`
+    ///
+    fn mantissa_at(&self, scale: i32) -> Option<BigInt> {
+        let exponent = i64::from(self.scale) - i64::from(scale);
+        if exponent >= 0 {
+            Some(&self.mantissa * pow10(exponent))
+        } else if below_pow10(&self.mantissa, -exponent) {
+            self.is_zero().then(BigInt::zero)
+        } else {
+            let (mantissa, remainder) = self.mantissa.div_rem(&pow10(-exponent));
+            remainder.is_zero().then_some(mantissa)
+        }
+    }
+
+    /// Mantissas of `self` and `other` at their common (smallest) scale
+    ///
+    fn aligned(&self, other: &Self) -> (BigInt, BigInt, i32) {
+        let scale = cmp::min(self.scale, other.scale);
+        let lhs = &self.mantissa * pow10(i64::from(self.scale) - i64::from(scale));
+        let rhs = &other.mantissa * pow10(i64::from(other.scale) - i64::from(scale));
+        (lhs, rhs, scale)
+    }
+
+    /// Bounds `(low, high)` of the decimal magnitude, `10^low <= |value| < 10^high`
+    ///
+    /// Estimated from the bit length of the mantissa, which must not be zero.
+    ///
+    fn magnitude(&self) -> (i64, i64) {
+        let bits = i64::try_from(self.mantissa.bits()).unwrap_or(i64::MAX);
+        let scale = i64::from(self.scale);
+        // 2^(bits - 1) <= |mantissa| < 2^bits, 10^0.3 < 2 < 10^0.31
+        let low = scale.saturating_add(bits.saturating_sub(1).saturating_mul(3) / 10);
+        let high = scale.saturating_add(bits.saturating_mul(31).saturating_add(99) / 100);
+        (low, high)
+    }
+
+    fn round_to_nano(self) -> Self {
+        if self.scale >= NANO {
+            return self;
+        }
+        let exponent = i64::from(NANO) - i64::from(self.scale);
+        let mantissa = div_pow10_round_up(&self.mantissa, exponent);
+        Self {
+            mantissa,
+            scale: NANO,
+            ..self
+        }
+    }
+
+    /// Format of the result of binary operation, Kubernetes keeps the left hand side format
+    /// unless it is zero
+    ///
+    fn result_format(&self, other: &Self) -> Format {
+        if self.is_zero() {
+            other.format
+        } else {
+            self.format
+        }
+    }
+}
+
+fn decimal_suffix(exponent: i64) -> Option<&'static str> {
+    DECIMAL_SUFFIXES
+        .iter()
+        .find(|(e, _)| i64::from(*e) == exponent)
+        .map(|(_, suffix)| *suffix)
+}
+
+/// `10^exponent`, `exponent` being a non-negative difference of two `i32` scales
+///
+fn pow10(exponent: i64) -> BigInt {
+    let exponent = u32::try_from(exponent).expect("difference of i32 scales fits u32");
+    BigInt::from(10).pow(exponent)
+}
+
+/// Whether `|value| < 10^exponent` follows from the bit length of `value` alone
+///
+fn below_pow10(value: &BigInt, exponent: i64) -> bool {
+    // |value| < 2^bits <= 2^(3 * exponent) < 10^exponent
+    i64::try_from(value.bits()).is_ok_and(|bits| bits <= exponent.saturating_mul(3))
+}
+
+/// `numerator / 10^exponent` rounded up (away from zero), without computing `10^exponent`
+/// when the result is certainly within one unit of zero
+///
+fn div_pow10_round_up(numerator: &BigInt, exponent: i64) -> BigInt {
+    if below_pow10(numerator, exponent) {
+        numerator.signum()
+    } else {
+        div_round_up(numerator, &pow10(exponent))
+    }
+}
+
+fn div_round_up(numerator: &BigInt, denominator: &BigInt) -> BigInt {
+    let (quotient, remainder) = numerator.div_rem(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + numerator.signum()
+    }
+}
+
+impl str::FromStr for ParsedQuantity {
+    type Err = QuantityParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let parts = parser::parse(text).ok_or_else(|| QuantityParseError::new(text))?;
+        if let parser::Suffix::DecimalExponent(exponent) = parts.suffix {
+            if exponent.unsigned_abs() > MAX_EXPONENT {
+                return Err(QuantityParseError::new(text));
+            }
+        }
+
+        let digits = format!("0{}{}", parts.integer, parts.fraction);
+        let mut mantissa = digits
+            .parse::<BigInt>()
+            .map_err(|_| QuantityParseError::new(text))?
+            << parts.exp2();
+        if parts.negative {
+            mantissa = -mantissa;
+        }
+
+        let scale = i32::try_from(parts.exp10()).map_err(|_| QuantityParseError::new(text))?;
+        let format = match parts.suffix {
+            parser::Suffix::BinarySI(_) => Format::BinarySI,
+            parser::Suffix::DecimalSI(_) => Format::DecimalSI,
+            parser::Suffix::DecimalExponent(_) => Format::DecimalExponent,
+        };
+
+        Self::checked_new(mantissa, scale, format).ok_or_else(|| QuantityParseError::new(text))
+    }
+}
+
+impl fmt::Display for ParsedQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (number, suffix) = self.canonicalize();
+        write!(f, "{number}{suffix}")
+    }
+}
+
+impl TryFrom<&resource::Quantity> for ParsedQuantity {
+    type Error = QuantityParseError;
+
+    fn try_from(quantity: &resource::Quantity) -> Result<Self, Self::Error> {
+        quantity.0.parse()
+    }
+}
+
+impl TryFrom<resource::Quantity> for ParsedQuantity {
+    type Error = QuantityParseError;
+
+    fn try_from(quantity: resource::Quantity) -> Result<Self, Self::Error> {
+        Self::try_from(&quantity)
+    }
+}
+
+impl From<&ParsedQuantity> for resource::Quantity {
+    fn from(quantity: &ParsedQuantity) -> Self {
+        Self(quantity.to_string())
+    }
+}
+
+impl From<ParsedQuantity> for resource::Quantity {
+    fn from(quantity: ParsedQuantity) -> Self {
+        Self::from(&quantity)
+    }
+}
+
+impl From<i64> for ParsedQuantity {
+    fn from(value: i64) -> Self {
+        Self::new(value, 0, Format::DecimalSI)
+    }
+}
+
+impl PartialEq for ParsedQuantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for ParsedQuantity {}
+
+impl PartialOrd for ParsedQuantity {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ParsedQuantity {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        let sign = self.mantissa.sign().cmp(&other.mantissa.sign());
+        if sign.is_ne() || self.is_zero() {
+            return sign;
+        }
+
+        // Values of far apart magnitudes are ordered without aligning their scales
+        let (low, high) = self.magnitude();
+        let (other_low, other_high) = other.magnitude();
+        let magnitude = if high <= other_low {
+            cmp::Ordering::Less
+        } else if other_high <= low {
+            cmp::Ordering::Greater
+        } else {
+            let (lhs, rhs, _) = self.aligned(other);
+            return lhs.cmp(&rhs);
+        };
+        if self.is_negative() {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl ops::Add<&ParsedQuantity> for &ParsedQuantity {
+    type Output = ParsedQuantity;
+
+    fn add(self, other: &ParsedQuantity) -> Self::Output {
+        let (lhs, rhs, scale) = self.aligned(other);
+        ParsedQuantity::new(lhs + rhs, scale, self.result_format(other))
+    }
+}
+
+impl ops::Sub<&ParsedQuantity> for &ParsedQuantity {
+    type Output = ParsedQuantity;
+
+    fn sub(self, other: &ParsedQuantity) -> Self::Output {
+        let (lhs, rhs, scale) = self.aligned(other);
+        ParsedQuantity::new(lhs - rhs, scale, self.result_format(other))
+    }
+}
+
+impl ops::Add for ParsedQuantity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        &self + &other
+    }
+}
+
+impl ops::Sub for ParsedQuantity {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        &self - &other
+    }
+}
+
+impl ops::AddAssign<&Self> for ParsedQuantity {
+    fn add_assign(&mut self, other: &Self) {
+        *self = &*self + other;
+    }
+}
+
+impl ops::SubAssign<&Self> for ParsedQuantity {
+    fn sub_assign(&mut self, other: &Self) {
+        *self = &*self - other;
+    }
+}
+
+impl ops::Neg for ParsedQuantity {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            mantissa: -self.mantissa,
+            ..self
+        }
+    }
+}
+
+impl iter::Sum for ParsedQuantity {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |sum, quantity| sum + quantity)
+    }
+}
+
+impl<'a> iter::Sum<&'a Self> for ParsedQuantity {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |sum, quantity| &sum + quantity)
+    }
+}
+
+impl Serialize for ParsedQuantity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ParsedQuantity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use k8s::serde_json as json;
+
+    fn parsed(text: &str) -> ParsedQuantity {
+        text.parse().unwrap()
+    }
+
+    fn canonical(text: &str) -> String {
+        parsed(text).to_string()
+    }
+
+    #[test]
+    fn canonical_decimal_si() {
+        assert_eq!(canonical("100m"), "100m");
+        assert_eq!(canonical("1000m"), "1");
+        assert_eq!(canonical("0.1"), "100m");
+        assert_eq!(canonical("1500k"), "1500k");
+        assert_eq!(canonical("1.5M"), "1500k");
+        assert_eq!(canonical("12E"), "12E");
+        assert_eq!(canonical("196382978n"), "196382978n");
+        assert_eq!(canonical("-0.5"), "-500m");
+    }
+
+    #[test]
+    fn canonical_binary_si() {
+        assert_eq!(canonical("1.5Gi"), "1536Mi");
+        assert_eq!(canonical("1024Ki"), "1Mi");
+        assert_eq!(canonical("0.5Ki"), "512");
+        assert_eq!(canonical("1536"), "1536");
+        assert_eq!(canonical("1.0001Ki"), "1024102400u");
+        assert_eq!(canonical("-1Ki"), "-1Ki");
+        assert_eq!(canonical("1048576Ei"), "1048576Ei");
+    }
+
+    #[test]
+    fn canonical_decimal_exponent() {
+        assert_eq!(canonical("1e3"), "1e3");
+        assert_eq!(canonical("1E6"), "1e6");
+        assert_eq!(canonical("10e2"), "1e3");
+        assert_eq!(canonical("100e-2"), "1");
+        assert_eq!(canonical("1e-1"), "100e-3");
+    }
+
+    #[test]
+    fn canonical_zero() {
+        assert_eq!(canonical("0"), "0");
+        assert_eq!(canonical("0Ki"), "0");
+        assert_eq!(canonical("-0.0m"), "0");
+    }
+
+    #[test]
+    fn rounds_up_to_nano() {
+        assert_eq!(canonical("1e-10"), "1e-9");
+        assert_eq!(canonical("0.0000000001"), "1n");
+        assert_eq!(canonical("-0.0000000001"), "-1n");
+        assert_eq!(canonical("1.0000000001"), "1000000001n");
+    }
+
+    #[test]
+    fn no_overflow() {
+        let big = parsed("8Ei") + parsed("8Ei");
+        assert_eq!(big.to_string(), "16Ei");
+        assert_eq!(big.to_scaled_i64(0), None);
+        assert_eq!(big.to_scaled_bigint(0), Some(BigInt::from(16_u128 << 60)));
+        assert_eq!(canonical("1000E"), "1e21");
+    }
+
+    #[test]
+    fn huge_exponents() {
+        assert!("1e-999999999".parse::<ParsedQuantity>().is_err());
+        assert!("1e999999999".parse::<ParsedQuantity>().is_err());
+        assert_eq!(canonical("1e-1000"), "1e-9");
+        assert_eq!(parsed("1e1000").to_scaled_i64(-9), None);
+        assert_eq!(parsed("1e1000").to_scaled_i64(0), None);
+
+        let tiny = ParsedQuantity::new(-1, -999_999_999, Format::DecimalSI);
+        assert_eq!(tiny.to_string(), "-1n");
+        assert!(ParsedQuantity::checked_new(1, 1001, Format::DecimalSI).is_none());
+        assert!(ParsedQuantity::checked_new(1, i32::MAX, Format::DecimalSI).is_none());
+        assert!(parsed("1e1000").mul_f64(10.0).is_none());
+    }
+
+    #[test]
+    fn huge_rescale() {
+        let zero = ParsedQuantity::zero(Format::DecimalSI);
+        assert_eq!(zero.to_scaled_i64(-1_000_000_000), Some(0));
+        assert_eq!(zero.to_scaled_bigint(i32::MIN), Some(BigInt::zero()));
+
+        let one = parsed("1");
+        assert_eq!(one.to_scaled_i64(-1_000_000_000), None);
+        assert_eq!(one.to_scaled_bigint(-1_000_000_000), None);
+        assert_eq!(one.to_scaled_bigint(i32::MAX), Some(BigInt::from(1)));
+        assert_eq!(
+            one.to_scaled_bigint(-1009),
+            Some(BigInt::from(10).pow(1009))
+        );
+        assert_eq!(one.to_scaled_bigint(-1010), None);
+        assert_eq!(
+            parsed("1e1000").to_scaled_bigint(-9),
+            Some(BigInt::from(10).pow(1009))
+        );
+    }
+
+    #[test]
+    fn huge_scale_arithmetic() {
+        let huge = parsed("1e1000");
+        let nano = parsed("1n");
+        assert!(huge > nano);
+        assert!(-huge.clone() < -nano.clone());
+        assert_eq!(huge.cmp(&huge), cmp::Ordering::Equal);
+
+        let sum = &huge + &nano;
+        assert!(sum > huge);
+        assert_eq!(sum.scale(), -9);
+        assert_eq!(&sum - &nano, huge);
+    }
+
+    #[test]
+    fn add_sub() {
+        assert_eq!((parsed("1Gi") + parsed("512Mi")).to_string(), "1536Mi");
+        assert_eq!((parsed("250m") + parsed("1")).to_string(), "1250m");
+        assert_eq!((parsed("1") - parsed("250m")).to_string(), "750m");
+        assert_eq!((parsed("0") + parsed("1Ki")).to_string(), "1Ki");
+        assert_eq!((parsed("1Ki") - parsed("1Ki")).to_string(), "0");
+    }
+
+    #[test]
+    fn sum() {
+        let total = ["1Mi", "1Mi", "2Mi"]
+            .into_iter()
+            .map(parsed)
+            .sum::<ParsedQuantity>();
+        assert_eq!(total.to_string(), "4Mi");
+    }
+
+    #[test]
+    fn mul_f64() {
+        assert_eq!(parsed("1Gi").mul_f64(0.5).unwrap().to_string(), "512Mi");
+        assert_eq!(parsed("100m").mul_f64(2.5).unwrap().to_string(), "250m");
+        assert_eq!(parsed("1").mul_f64(-1e-10).unwrap().to_string(), "-1n");
+        assert!(parsed("1").mul_f64(f64::NAN).is_none());
+        assert!(parsed("1").mul_f64(f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn compare() {
+        assert_eq!(parsed("1Ki"), parsed("1024"));
+        assert_eq!(parsed("1"), parsed("1000m"));
+        assert!(parsed("1k") < parsed("1Ki"));
+        assert!(parsed("-1") < parsed("1n"));
+        assert_eq!(parsed("1e3").cmp(&parsed("1k")), cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn scaled() {
+        let q = parsed("1.5");
+        assert_eq!(q.to_scaled_i64(0), Some(2));
+        assert_eq!(q.to_scaled_i64(-3), Some(1500));
+        assert_eq!(q.to_scaled_i64(-9), Some(1_500_000_000));
+        assert_eq!(parsed("-1.5").to_scaled_i64(0), Some(-2));
+        assert_eq!(q.to_f64(), 1.5);
+    }
+
+    #[test]
+    fn quantity_round_trip() {
+        let quantity = resource::Quantity("2048Ki".to_string());
+        let parsed = ParsedQuantity::try_from(&quantity).unwrap();
+        assert_eq!(parsed.format(), Format::BinarySI);
+        assert_eq!(resource::Quantity::from(parsed).0, "2Mi");
+    }
+
+    #[test]
+    fn serde() {
+        let q = json::from_str::<ParsedQuantity>(r#""1.5Gi""#).unwrap();
+        assert_eq!(json::to_string(&q).unwrap(), r#""1536Mi""#);
+        json::from_str::<ParsedQuantity>(r#""1.5Gb""#).unwrap_err();
+    }
+}
@@ -0,0 +1,184 @@
+//! Parser for the Kubernetes quantity grammar
+//!
+//! ```text
+//! <quantity>        ::= <signedNumber><suffix>
+//! <signedNumber>    ::= <number> | "+" <number> | "-" <number>
+//! <number>          ::= <digits> | <digits> "." | <digits> "." <digits> | "." <digits>
+//! <suffix>          ::= <binarySI> | <decimalExponent> | <decimalSI>
+//! <binarySI>        ::= Ki | Mi | Gi | Ti | Pi | Ei
+//! <decimalSI>       ::= n | u | m | "" | k | M | G | T | P | E
+//! <decimalExponent> ::= "e" <signedNumber> | "E" <signedNumber>
+//! ```
+//!
+
+/// Quantity split into its components, without any loss of precision
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Parts<'a> {
+    /// whether the quantity had a leading `-`
+    ///
+    pub(crate) negative: bool,
+    /// integer digits with leading zeros stripped (may be empty)
+    ///
+    pub(crate) integer: &'a str,
+    /// fractional digits (may be empty)
+    ///
+    pub(crate) fraction: &'a str,
+    pub(crate) suffix: Suffix,
+}
+
+/// Multiplier denoted by the quantity suffix
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Suffix {
+    /// `Ki`, `Mi`, ... - power of two
+    ///
+    BinarySI(u32),
+    /// `n`, `u`, `m`, `k`, `M`, ... - power of ten
+    ///
+    DecimalSI(i32),
+    /// `e3`, `E-6`, ... - power of ten
+    ///
+    DecimalExponent(i32),
+}
+
+/// Parse `text` according to the Kubernetes quantity grammar
+///
+pub(crate) fn parse(text: &str) -> Option<Parts<'_>> {
+    let (negative, rest) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    let (integer, rest) = split_digits(rest);
+    let (fraction, rest) = match rest.strip_prefix('.') {
+        Some(rest) => split_digits(rest),
+        None => ("", rest),
+    };
+
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let integer = integer.trim_start_matches('0');
+    let suffix = parse_suffix(rest)?;
+
+    Some(Parts {
+        negative,
+        integer,
+        fraction,
+        suffix,
+    })
+}
+
+fn split_digits(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+fn parse_suffix(suffix: &str) -> Option<Suffix> {
+    let suffix = match suffix {
+        "Ki" => Suffix::BinarySI(10),
+        "Mi" => Suffix::BinarySI(20),
+        "Gi" => Suffix::BinarySI(30),
+        "Ti" => Suffix::BinarySI(40),
+        "Pi" => Suffix::BinarySI(50),
+        "Ei" => Suffix::BinarySI(60),
+        "n" => Suffix::DecimalSI(-9),
+        "u" => Suffix::DecimalSI(-6),
+        "m" => Suffix::DecimalSI(-3),
+        "" => Suffix::DecimalSI(0),
+        "k" => Suffix::DecimalSI(3),
+        "M" => Suffix::DecimalSI(6),
+        "G" => Suffix::DecimalSI(9),
+        "T" => Suffix::DecimalSI(12),
+        "P" => Suffix::DecimalSI(15),
+        "E" => Suffix::DecimalSI(18),
+        other => {
+            let exponent = other
+                .strip_prefix('e')
+                .or_else(|| other.strip_prefix('E'))?;
+            let digits = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Suffix::DecimalExponent(exponent.parse().ok()?)
+        }
+    };
+
+    Some(suffix)
+}
+
+impl Parts<'_> {
+    /// Power of ten the concatenated `integer` and `fraction` digits are multiplied by
+    ///
+    pub(crate) fn exp10(&self) -> i64 {
+        let exponent = match self.suffix {
+            Suffix::BinarySI(_) => 0,
+            Suffix::DecimalSI(exponent) | Suffix::DecimalExponent(exponent) => exponent,
+        };
+        i64::from(exponent) - self.fraction.len() as i64
+    }
+
+    /// Power of two the concatenated `integer` and `fraction` digits are multiplied by
+    ///
+    pub(crate) fn exp2(&self) -> u32 {
+        match self.suffix {
+            Suffix::BinarySI(exponent) => exponent,
+            Suffix::DecimalSI(_) | Suffix::DecimalExponent(_) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer() {
+        let parts = parse("1024").unwrap();
+        assert!(!parts.negative);
+        assert_eq!(parts.integer, "1024");
+        assert_eq!(parts.fraction, "");
+        assert_eq!(parts.suffix, Suffix::DecimalSI(0));
+    }
+
+    #[test]
+    fn signed_fraction() {
+        let parts = parse("-001.250Gi").unwrap();
+        assert!(parts.negative);
+        assert_eq!(parts.integer, "1");
+        assert_eq!(parts.fraction, "250");
+        assert_eq!(parts.suffix, Suffix::BinarySI(30));
+    }
+
+    #[test]
+    fn decimal_exponent() {
+        assert_eq!(parse("1e3").unwrap().suffix, Suffix::DecimalExponent(3));
+        assert_eq!(parse("1E-3").unwrap().suffix, Suffix::DecimalExponent(-3));
+        assert_eq!(parse("1e+3").unwrap().suffix, Suffix::DecimalExponent(3));
+    }
+
+    #[test]
+    fn exa_is_not_exponent() {
+        assert_eq!(parse("12E").unwrap().suffix, Suffix::DecimalSI(18));
+    }
+
+    #[test]
+    fn partial_numbers() {
+        assert_eq!(parse(".5").unwrap().fraction, "5");
+        assert_eq!(parse("5.").unwrap().integer, "5");
+    }
+
+    #[test]
+    fn invalid() {
+        for text in [
+            "", "-", ".", "Ki", "1K", "1ki", "1 Ki", "1e", "1e+", "1e3.5", "1.2.3", "1Kib",
+        ] {
+            assert_eq!(parse(text), None, "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Recording and replay of metrics snapshots in JSON Lines format
+//!
+//! Every line holds a single `Snapshot`. Files with a `.gz` extension are gzip-compressed,
+//! which requires the `gzip` feature. Appending to a compressed file adds another gzip
+//! member, the reader decodes all of them.
+//!
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use k8s::serde_json as json;
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+use super::*;
+
+/// Metrics listed at a point in time
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: metav1::Time,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeMetrics>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pods: Vec<PodMetrics>,
+}
+
+impl Snapshot {
+    pub fn new(time: Timestamp) -> Self {
+        Self {
+            time: metav1::Time(time),
+            nodes: default(),
+            pods: default(),
+        }
+    }
+
+    /// Empty snapshot taken now
+    ///
+    pub fn now() -> Self {
+        Self::new(Timestamp::now())
+    }
+
+    pub fn nodes(self, nodes: Vec<NodeMetrics>) -> Self {
+        Self { nodes, ..self }
+    }
+
+    pub fn pods(self, pods: Vec<PodMetrics>) -> Self {
+        Self { pods, ..self }
+    }
+}
+
+/// Appends snapshots to a JSON Lines stream
+///
+/// Call `finish()` once done, dropping the recorder discards errors of the final writes.
+///
+pub struct Recorder<W: Write> {
+    writer: Encoder<W>,
+}
+
+enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+}
+
+impl<W: Write> fmt::Debug for Recorder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(writer: W) -> Self {
+        let writer = Encoder::Plain(writer);
+        Self { writer }
+    }
+
+    /// Recorder compressing its output with gzip
+    ///
+    #[cfg(feature = "gzip")]
+    pub fn gzip(writer: W) -> Self {
+        let writer = Encoder::Gzip(flate2::write::GzEncoder::new(writer, default()));
+        Self { writer }
+    }
+
+    /// Write `snapshot` as a single line
+    ///
+    pub fn record(&mut self, snapshot: &Snapshot) -> Result<(), RecordingError> {
+        let mut line =
+            json::to_vec(snapshot).map_err(|source| RecordingError::Json { line: None, source })?;
+        line.push(b'\n');
+        self.writer().write_all(&line)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), RecordingError> {
+        self.writer().flush()?;
+        Ok(())
+    }
+
+    /// Complete the output, writing the gzip trailer if compressed, and flush it
+    ///
+    #[cfg_attr(not(feature = "gzip"), expect(clippy::infallible_destructuring_match))]
+    pub fn finish(self) -> Result<W, RecordingError> {
+        let mut writer = match self.writer {
+            Encoder::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match &mut self.writer {
+            Encoder::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder,
+        }
+    }
+}
+
+impl Recorder<io::BufWriter<fs::File>> {
+    /// Append to the file at `path`, creating it if needed, gzip-compressed if it ends with `.gz`
+    ///
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let path = path.as_ref();
+        let gzip = is_gzip(path)?;
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let file = io::BufWriter::new(file);
+        if gzip {
+            #[cfg(feature = "gzip")]
+            return Ok(Self::gzip(file));
+        }
+        Ok(Self::new(file))
+    }
+}
+
+/// Reads snapshots back from a JSON Lines stream, in the order they were recorded
+///
+pub struct Reader<R> {
+    reader: R,
+    line: usize,
+    buffer: String,
+    pending: Option<Snapshot>,
+}
+
+impl<R> fmt::Debug for Reader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader")
+            .field("line", &self.line)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            buffer: String::new(),
+            pending: None,
+        }
+    }
+
+    /// Skip snapshots taken before `time`
+    ///
+    /// Snapshots are read sequentially, so this only moves forward.
+    ///
+    pub fn seek(&mut self, time: Timestamp) -> Result<(), RecordingError> {
+        while let Some(snapshot) = self.next().transpose()? {
+            if snapshot.time.0 >= time {
+                self.pending = Some(snapshot);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<Snapshot>, RecordingError> {
+        loop {
+            self.buffer.clear();
+            if self.reader.read_line(&mut self.buffer)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let text = self.buffer.trim();
+            if !text.is_empty() {
+                return json::from_str(text)
+                    .map(Some)
+                    .map_err(|source| RecordingError::Json {
+                        line: Some(self.line),
+                        source,
+                    });
+            }
+        }
+    }
+}
+
+impl Reader<Box<dyn BufRead + Send>> {
+    /// Read the file at `path`, gzip-compressed if it ends with `.gz`
+    ///
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let path = path.as_ref();
+        let gzip = is_gzip(path)?;
+        let file = fs::File::open(path)?;
+        Ok(Self::new(decoder(io::BufReader::new(file), gzip)))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Snapshot, RecordingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.pending.take() {
+            Some(snapshot) => Some(Ok(snapshot)),
+            None => self.read().transpose(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("Invalid snapshot{}: {source}", line.map(|line| format!(" at line {line}")).unwrap_or_default())]
+    Json {
+        line: Option<usize>,
+        source: json::Error,
+    },
+}
+
+/// Whether `path` names a gzip-compressed file, failing if gzip is not supported
+///
+fn is_gzip(path: &Path) -> io::Result<bool> {
+    let gzip = path.extension().is_some_and(|extension| extension == "gz");
+    if gzip && cfg!(not(feature = "gzip")) {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "gzip-compressed recordings require the `gzip` feature",
+        ))
+    } else {
+        Ok(gzip)
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn decoder<R: BufRead + Send + 'static>(reader: R, gzip: bool) -> Box<dyn BufRead + Send> {
+    if gzip {
+        let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+        Box::new(io::BufReader::new(decoder))
+    } else {
+        Box::new(reader)
+    }
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decoder<R: BufRead + Send + 'static>(reader: R, _gzip: bool) -> Box<dyn BufRead + Send> {
+    Box::new(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use v1beta1::Usage;
+
+    use super::*;
+
+    fn snapshot(second: i64) -> Snapshot {
+        let time = Timestamp::from_second(1665316280 + second).unwrap();
+        let node = NodeMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some("node-1".to_string()),
+                ..default()
+            },
+            timestamp: metav1::Time(time),
+            window: time::Duration::from_secs(10),
+            usage: Usage {
+                cpu: Cpu::from_millicores(250).into(),
+                memory: Memory::from_mib(512).into(),
+            },
+        };
+        Snapshot::new(time).nodes(vec![node])
+    }
+
+    fn recording() -> Vec<u8> {
+        let mut recorder = Recorder::new(vec![]);
+        for second in [0, 15, 30] {
+            recorder.record(&snapshot(second)).unwrap();
+        }
+        recorder.finish().unwrap()
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("k8s-metrics-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn format() {
+        let recording = String::from_utf8(recording()).unwrap();
+        let lines = recording.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        let line = json::from_str::<json::Value>(lines[0]).unwrap();
+        assert_eq!(line["time"], "2022-10-09T11:51:20Z");
+        assert_eq!(line["nodes"][0]["usage"]["cpu"], "250m");
+        assert_eq!(line.get("pods"), None);
+    }
+
+    #[test]
+    fn replay() {
+        let recording = recording();
+        let snapshots = Reader::new(recording.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(snapshots, [snapshot(0), snapshot(15), snapshot(30)]);
+    }
+
+    #[test]
+    fn seek() {
+        let recording = recording();
+        let mut reader = Reader::new(recording.as_slice());
+        reader.seek(snapshot(10).time.0).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), snapshot(15));
+
+        reader.seek(snapshot(30).time.0).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), snapshot(30));
+
+        reader.seek(snapshot(0).time.0).unwrap();
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn invalid_line() {
+        let mut recording = recording();
+        recording.extend_from_slice(b"\n{\"time\": 42}\n");
+        let err = Reader::new(recording.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert!(matches!(err, RecordingError::Json { line: Some(5), .. }));
+        assert!(err.to_string().starts_with("Invalid snapshot at line 5: "));
+    }
+
+    #[test]
+    fn file() {
+        let path = temp_path("recording.jsonl");
+        for second in [0, 15] {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder.record(&snapshot(second)).unwrap();
+            recorder.finish().unwrap();
+        }
+
+        let snapshots = Reader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(snapshots, [snapshot(0), snapshot(15)]);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        let path = temp_path("recording.jsonl.gz");
+        for second in [0, 15] {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder.record(&snapshot(second)).unwrap();
+            let file = recorder.finish().unwrap();
+            assert!(file.buffer().is_empty());
+        }
+
+        let compressed = fs::read(&path).unwrap();
+        assert_eq!(compressed[..2], [0x1f, 0x8b]);
+        let snapshots = Reader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(snapshots, [snapshot(0), snapshot(15)]);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn gzip_unsupported() {
+        let path = temp_path("unsupported.jsonl.gz");
+        let err = Recorder::create(&path).unwrap_err();
+        assert!(!path.exists());
+        assert!(
+            matches!(&err, RecordingError::Io(err) if err.kind() == io::ErrorKind::Unsupported)
+        );
+    }
+}
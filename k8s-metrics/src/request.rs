@@ -0,0 +1,144 @@
+use k8s::serde_json as json;
+
+use super::*;
+
+/// Error returned by response parsers of the request builders
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ResponseError {
+    /// API server responded with an error status
+    ///
+    #[error("API request failed ({}): {}", .0.code.unwrap_or_default(), .0.message.as_deref().unwrap_or_default())]
+    Api(Box<metav1::Status>),
+
+    /// Response body could not be decoded
+    ///
+    #[error("Failed to decode response: {0}")]
+    Json(#[from] json::Error),
+}
+
+impl ResponseError {
+    /// HTTP status code of the failed request
+    ///
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Self::Api(status) => status.code,
+            Self::Json(_) => None,
+        }
+    }
+}
+
+/// Build `GET` request for `uri`
+///
+pub(crate) fn get(uri: &str) -> Result<http::Request<Vec<u8>>, http::Error> {
+    http::Request::get(uri)
+        .header(http::header::ACCEPT, "application/json")
+        .body(vec![])
+}
+
+/// Append url-encoded `query` parameters to `path`
+///
+pub(crate) fn uri(path: &str, query: &[(&str, &str)]) -> String {
+    if query.is_empty() {
+        return path.to_string();
+    }
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(query)
+        .finish();
+    format!("{path}?{query}")
+}
+
+/// Percent-encode `text` for use as a single path segment
+///
+/// Leaves the same characters unescaped as Go's `url.PathEscape`, so `/`, `%`, `?`
+/// and `#` in names cannot change the meaning of the path.
+///
+pub(crate) fn segment(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'='
+            | b':'
+            | b'@' => char::from(byte).to_string(),
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decode response `body`, or the `Status` returned by API server on failure
+///
+pub(crate) fn parse<T>(status: http::StatusCode, body: &[u8]) -> Result<T, ResponseError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    if status.is_success() {
+        return json::from_slice(body).map_err(ResponseError::from);
+    }
+
+    let status = json::from_slice::<metav1::Status>(body).unwrap_or_else(|_| metav1::Status {
+        code: Some(status.as_u16().into()),
+        message: Some(String::from_utf8_lossy(body).into_owned()),
+        reason: status.canonical_reason().map(ToString::to_string),
+        status: Some("Failure".to_string()),
+        ..default()
+    });
+    Err(ResponseError::Api(Box::new(status)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_query() {
+        assert_eq!(uri("/apis", &[]), "/apis");
+        assert_eq!(
+            uri("/apis", &[("labelSelector", "app=web,tier in (a,b)")]),
+            "/apis?labelSelector=app%3Dweb%2Ctier+in+%28a%2Cb%29"
+        );
+    }
+
+    #[test]
+    fn path_segment() {
+        assert_eq!(segment("web-0"), "web-0");
+        assert_eq!(segment("http_requests:rate"), "http_requests:rate");
+        assert_eq!(segment("a/b"), "a%2Fb");
+        assert_eq!(segment("100%?#x y"), "100%25%3F%23x%20y");
+        assert_eq!(segment("ü"), "%C3%BC");
+    }
+
+    #[test]
+    fn get_request() {
+        let request = get("/apis?labelSelector=app%3Dweb").unwrap();
+        assert_eq!(request.method(), http::Method::GET);
+        assert_eq!(request.uri(), "/apis?labelSelector=app%3Dweb");
+        assert_eq!(request.headers()[http::header::ACCEPT], "application/json");
+    }
+
+    #[test]
+    fn parse_status() {
+        let body = br#"{"kind":"Status","apiVersion":"v1","metadata":{},"status":"Failure","message":"the server could not find the metric","reason":"NotFound","code":404}"#;
+        let err = parse::<json::Value>(http::StatusCode::NOT_FOUND, body).unwrap_err();
+        assert_eq!(err.code(), Some(404));
+        assert_eq!(
+            err.to_string(),
+            "API request failed (404): the server could not find the metric"
+        );
+
+        let err = parse::<json::Value>(http::StatusCode::SERVICE_UNAVAILABLE, b"unavailable")
+            .unwrap_err();
+        assert_eq!(err.code(), Some(503));
+
+        let err = parse::<json::Value>(http::StatusCode::OK, b"{").unwrap_err();
+        assert!(matches!(err, ResponseError::Json(_)));
+    }
+}
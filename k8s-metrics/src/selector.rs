@@ -0,0 +1,376 @@
+//! Kubernetes label selectors, as used in `labelSelector` query parameters
+//!
+//! ```text
+//! <selector>    ::= "" | <requirement> | <requirement> "," <selector>
+//! <requirement> ::= <key> | "!" <key> | <key> <op> <value> | <key> ("in" | "notin") "(" <values> ")"
+//! <op>          ::= "=" | "==" | "!=" | ">" | "<"
+//! ```
+//!
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str;
+
+use super::*;
+
+/// Parsed label selector, e.g. `app=web,tier in (frontend,backend),!canary`
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Requirement {
+    Exists(String),
+    DoesNotExist(String),
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, BTreeSet<String>),
+    NotIn(String, BTreeSet<String>),
+    GreaterThan(String, i64),
+    LessThan(String, i64),
+}
+
+impl Selector {
+    /// Selector matching everything
+    ///
+    pub fn everything() -> Self {
+        Self::default()
+    }
+
+    /// Selector matches everything
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// `labels` satisfy all requirements of this selector
+    ///
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| requirement.matches(labels))
+    }
+
+    /// Labels of `object` satisfy all requirements of this selector
+    ///
+    pub fn matches_object<K>(&self, object: &K) -> bool
+    where
+        K: k8s::Metadata<Ty = metav1::ObjectMeta>,
+    {
+        match &object.metadata().labels {
+            Some(labels) => self.matches(labels),
+            None => self.matches(&BTreeMap::new()),
+        }
+    }
+}
+
+impl Requirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Self::Exists(key) => labels.contains_key(key),
+            Self::DoesNotExist(key) => !labels.contains_key(key),
+            Self::Equals(key, value) => labels.get(key) == Some(value),
+            Self::NotEquals(key, value) => labels.get(key) != Some(value),
+            Self::In(key, values) => labels.get(key).is_some_and(|value| values.contains(value)),
+            Self::NotIn(key, values) => {
+                !labels.get(key).is_some_and(|value| values.contains(value))
+            }
+            Self::GreaterThan(key, bound) => {
+                integer(labels, key).is_some_and(|value| value > *bound)
+            }
+            Self::LessThan(key, bound) => integer(labels, key).is_some_and(|value| value < *bound),
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(key) = text.strip_prefix('!') {
+            return Some(Self::DoesNotExist(key_of(key.trim())?));
+        }
+
+        let end = text
+            .find(|c: char| c.is_whitespace() || "=!<>(".contains(c))
+            .unwrap_or(text.len());
+        let key = key_of(&text[..end])?;
+        let rest = text[end..].trim_start();
+
+        let requirement = if rest.is_empty() {
+            Self::Exists(key)
+        } else if let Some(value) = rest.strip_prefix("==").or_else(|| rest.strip_prefix('=')) {
+            Self::Equals(key, value_of(value)?)
+        } else if let Some(value) = rest.strip_prefix("!=") {
+            Self::NotEquals(key, value_of(value)?)
+        } else if let Some(value) = rest.strip_prefix('>') {
+            Self::GreaterThan(key, value.trim().parse().ok()?)
+        } else if let Some(value) = rest.strip_prefix('<') {
+            Self::LessThan(key, value.trim().parse().ok()?)
+        } else if let Some(values) = rest.strip_prefix("notin") {
+            Self::NotIn(key, values_of(values)?)
+        } else if let Some(values) = rest.strip_prefix("in") {
+            Self::In(key, values_of(values)?)
+        } else {
+            return None;
+        };
+        Some(requirement)
+    }
+}
+
+fn integer(labels: &BTreeMap<String, String>, key: &str) -> Option<i64> {
+    labels.get(key)?.parse().ok()
+}
+
+fn key_of(text: &str) -> Option<String> {
+    let valid = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c));
+    valid.then(|| text.to_string())
+}
+
+fn value_of(text: &str) -> Option<String> {
+    let text = text.trim();
+    let valid = text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    valid.then(|| text.to_string())
+}
+
+fn values_of(text: &str) -> Option<BTreeSet<String>> {
+    let values = text
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split(',')
+        .map(value_of)
+        .collect::<Option<BTreeSet<_>>>()?;
+    Some(values)
+}
+
+/// Split `text` on commas outside of parentheses
+///
+fn split_requirements(text: &str) -> Vec<&str> {
+    let mut requirements = vec![];
+    let mut depth = 0_u32;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                requirements.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    requirements.push(&text[start..]);
+    requirements
+}
+
+impl str::FromStr for Selector {
+    type Err = SelectorParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.trim().is_empty() {
+            return Ok(Self::everything());
+        }
+        let requirements = split_requirements(text)
+            .into_iter()
+            .map(|requirement| {
+                Requirement::parse(requirement).ok_or_else(|| SelectorParseError::new(requirement))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { requirements })
+    }
+}
+
+impl TryFrom<&metav1::LabelSelector> for Selector {
+    type Error = SelectorParseError;
+
+    fn try_from(selector: &metav1::LabelSelector) -> Result<Self, Self::Error> {
+        let labels = selector
+            .match_labels
+            .iter()
+            .flatten()
+            .map(|(key, value)| Ok(Requirement::Equals(key.clone(), value.clone())));
+        let expressions = selector
+            .match_expressions
+            .iter()
+            .flatten()
+            .map(|expression| {
+                let key = expression.key.clone();
+                let values = expression.values.iter().flatten().cloned().collect();
+                match expression.operator.as_str() {
+                    "In" => Ok(Requirement::In(key, values)),
+                    "NotIn" => Ok(Requirement::NotIn(key, values)),
+                    "Exists" => Ok(Requirement::Exists(key)),
+                    "DoesNotExist" => Ok(Requirement::DoesNotExist(key)),
+                    operator => Err(SelectorParseError::new(operator)),
+                }
+            });
+        let requirements = labels.chain(expressions).collect::<Result<_, _>>()?;
+        Ok(Self { requirements })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, requirement) in self.requirements.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{requirement}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &BTreeSet<String>| values.iter().cloned().collect::<Vec<_>>().join(",");
+        match self {
+            Self::Exists(key) => write!(f, "{key}"),
+            Self::DoesNotExist(key) => write!(f, "!{key}"),
+            Self::Equals(key, value) => write!(f, "{key}={value}"),
+            Self::NotEquals(key, value) => write!(f, "{key}!={value}"),
+            Self::In(key, values) => write!(f, "{key} in ({})", join(values)),
+            Self::NotIn(key, values) => write!(f, "{key} notin ({})", join(values)),
+            Self::GreaterThan(key, value) => write!(f, "{key}>{value}"),
+            Self::LessThan(key, value) => write!(f, "{key}<{value}"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid label selector: {0}")]
+pub struct SelectorParseError(String);
+
+impl SelectorParseError {
+    fn new(text: &str) -> Self {
+        Self(text.trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+        labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn selector(text: &str) -> Selector {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn equality() {
+        let web = labels(&[("app", "web"), ("tier", "frontend")]);
+        assert!(selector("app=web").matches(&web));
+        assert!(selector("app == web, tier=frontend").matches(&web));
+        assert!(!selector("app=db").matches(&web));
+        assert!(selector("app!=db").matches(&web));
+        assert!(selector("release!=canary").matches(&web));
+        assert!(!selector("app!=web").matches(&web));
+    }
+
+    #[test]
+    fn set_based() {
+        let web = labels(&[("app", "web"), ("tier", "frontend")]);
+        assert!(selector("tier in (frontend, backend)").matches(&web));
+        assert!(!selector("tier notin (frontend,backend)").matches(&web));
+        assert!(selector("release notin (canary)").matches(&web));
+        assert!(!selector("release in (canary)").matches(&web));
+        assert!(selector("app,!release").matches(&web));
+        assert!(!selector("app,!tier").matches(&web));
+    }
+
+    #[test]
+    fn numeric() {
+        let labels = labels(&[("replicas", "3")]);
+        assert!(selector("replicas>2").matches(&labels));
+        assert!(!selector("replicas<3").matches(&labels));
+        assert!(!selector("generation>1").matches(&labels));
+    }
+
+    #[test]
+    fn everything() {
+        let selector = selector(" ");
+        assert!(selector.is_empty());
+        assert!(selector.matches(&BTreeMap::new()));
+        assert_eq!(selector, Selector::everything());
+    }
+
+    #[test]
+    fn prefixed_key() {
+        let labels = labels(&[("app.kubernetes.io/name", "web")]);
+        assert!(selector("app.kubernetes.io/name=web").matches(&labels));
+    }
+
+    #[test]
+    fn invalid() {
+        for text in [
+            "app=web,",
+            "app in web",
+            "app in (web",
+            "=web",
+            "app=web=db",
+            "app ~ web",
+            "replicas>three",
+            "!",
+        ] {
+            text.parse::<Selector>().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn display() {
+        let text = "app=web,tier in (backend,frontend),!canary,release notin (v1),replicas>1";
+        assert_eq!(selector(text).to_string(), text);
+        assert_eq!(
+            selector("tier in ( frontend , backend )").to_string(),
+            "tier in (backend,frontend)"
+        );
+    }
+
+    #[test]
+    fn from_label_selector() {
+        let label_selector = metav1::LabelSelector {
+            match_labels: Some(labels(&[("app", "web")])),
+            match_expressions: Some(vec![metav1::LabelSelectorRequirement {
+                key: "tier".to_string(),
+                operator: "In".to_string(),
+                values: Some(vec!["frontend".to_string()]),
+            }]),
+        };
+        let selector = Selector::try_from(&label_selector).unwrap();
+        assert_eq!(selector.to_string(), "app=web,tier in (frontend)");
+
+        let label_selector = metav1::LabelSelector {
+            match_expressions: Some(vec![metav1::LabelSelectorRequirement {
+                key: "tier".to_string(),
+                operator: "Gt".to_string(),
+                values: None,
+            }]),
+            ..default()
+        };
+        Selector::try_from(&label_selector).unwrap_err();
+    }
+
+    #[test]
+    fn matches_object() {
+        let pod = corev1::Pod {
+            metadata: metav1::ObjectMeta {
+                labels: Some(labels(&[("app", "web")])),
+                ..default()
+            },
+            ..default()
+        };
+        assert!(selector("app=web").matches_object(&pod));
+        assert!(selector("!app").matches_object(&corev1::Pod::default()));
+    }
+}
@@ -0,0 +1,295 @@
+//! Building blocks for metrics adapters
+//!
+//! Each API is served by a `tower_service::Service` backed by a provider trait,
+//! so that adapters only need to implement the provider and hook the service
+//! into their HTTP server of choice (e.g. `hyper` via `hyper_util::service::TowerToHyperService`).
+//!
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use k8s::serde_json as json;
+
+use super::*;
+
+pub use custom::{CustomMetricsProvider, CustomMetricsService};
+pub use external::{ExternalMetricsProvider, ExternalMetricsService};
+pub use resource_metrics::{ResourceMetricsProvider, ResourceMetricsService};
+
+mod custom;
+mod external;
+mod resource_metrics;
+
+/// Body of responses produced by the metrics services
+///
+pub type ResponseBody = Full<Bytes>;
+
+/// Error returned by metrics providers, reported to clients as `metav1::Status`
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    /// Requested metric or object does not exist
+    ///
+    #[error("{0}")]
+    NotFound(String),
+
+    /// Request cannot be served, e.g. because of malformed selector
+    ///
+    #[error("{0}")]
+    BadRequest(String),
+
+    /// Provider failed to collect metrics
+    ///
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ProviderError {
+    fn code(&self) -> http::StatusCode {
+        match self {
+            Self::NotFound(_) => http::StatusCode::NOT_FOUND,
+            Self::BadRequest(_) => http::StatusCode::BAD_REQUEST,
+            Self::Internal(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NotFound",
+            Self::BadRequest(_) => "BadRequest",
+            Self::Internal(_) => "InternalError",
+        }
+    }
+
+    fn into_response(self) -> http::Response<ResponseBody> {
+        let code = self.code();
+        let status = metav1::Status {
+            code: Some(code.as_u16().into()),
+            message: Some(self.to_string()),
+            reason: Some(self.reason().to_string()),
+            status: Some("Failure".to_string()),
+            ..default()
+        };
+        respond(code, &status)
+    }
+}
+
+/// Query parameters understood by the metrics APIs
+///
+#[derive(Debug, Default)]
+struct Query {
+    label_selector: Option<String>,
+    metric_label_selector: Option<String>,
+}
+
+impl Query {
+    fn parse<B>(request: &http::Request<B>) -> Self {
+        let query = request.uri().query().unwrap_or_default();
+        form_urlencoded::parse(query.as_bytes())
+            .filter(|(_, value)| !value.is_empty())
+            .fold(Self::default(), |query, (key, value)| match key.as_ref() {
+                "labelSelector" => Self {
+                    label_selector: Some(value.into_owned()),
+                    ..query
+                },
+                "metricLabelSelector" => Self {
+                    metric_label_selector: Some(value.into_owned()),
+                    ..query
+                },
+                _ => query,
+            })
+    }
+}
+
+/// Percent-decoded path segments of `request` below `prefix`, `None` if not below `prefix`
+///
+fn segments<B>(request: &http::Request<B>, prefix: &str) -> Option<Vec<String>> {
+    let path = request.uri().path().strip_prefix(prefix)?;
+    if !path.is_empty() && !path.starts_with('/') {
+        return None;
+    }
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(decode)
+        .collect()
+}
+
+/// Percent-decoded path `segment`, `None` unless it decodes to UTF-8
+///
+/// Malformed escapes are kept as they are.
+///
+fn decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// `APIGroup` document served at `/apis/{group}`
+///
+fn api_group(group: &str, version: &str) -> metav1::APIGroup {
+    let version = metav1::GroupVersionForDiscovery {
+        group_version: format!("{group}/{version}"),
+        version: version.to_string(),
+    };
+    metav1::APIGroup {
+        name: group.to_string(),
+        preferred_version: Some(version.clone()),
+        server_address_by_client_cidrs: None,
+        versions: vec![version],
+    }
+}
+
+fn respond<T: Serialize>(code: http::StatusCode, body: &T) -> http::Response<ResponseBody> {
+    let body = json::to_vec(body).expect("metrics types serialize to JSON");
+    http::Response::builder()
+        .status(code)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .expect("valid response")
+}
+
+fn ok<T: Serialize>(body: &T) -> http::Response<ResponseBody> {
+    respond(http::StatusCode::OK, body)
+}
+
+fn not_found(path: &str) -> http::Response<ResponseBody> {
+    ProviderError::NotFound(format!(
+        "the server could not find the requested resource {path}"
+    ))
+    .into_response()
+}
+
+fn method_not_allowed() -> http::Response<ResponseBody> {
+    let code = http::StatusCode::METHOD_NOT_ALLOWED;
+    let status = metav1::Status {
+        code: Some(code.as_u16().into()),
+        message: Some(
+            "the server does not allow this method on the requested resource".to_string(),
+        ),
+        reason: Some("MethodNotAllowed".to_string()),
+        status: Some("Failure".to_string()),
+        ..default()
+    };
+    respond(code, &status)
+}
+
+/// Implements `tower_service::Service` for metrics service `$service` backed by provider `$provider`
+///
+macro_rules! service {
+    ($service:ident, $provider:ident) => {
+        impl<P> Clone for $service<P> {
+            fn clone(&self) -> Self {
+                Self {
+                    provider: self.provider.clone(),
+                }
+            }
+        }
+
+        impl<P> std::fmt::Debug for $service<P> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($service)).finish_non_exhaustive()
+            }
+        }
+
+        impl<P: $provider, B> tower_service::Service<http::Request<B>> for $service<P> {
+            type Response = http::Response<ResponseBody>;
+            type Error = Infallible;
+            type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, request: http::Request<B>) -> Self::Future {
+                let service = self.clone();
+                let (parts, _body) = request.into_parts();
+                let request = http::Request::from_parts(parts, ());
+                Box::pin(async move { Ok(service.handle(&request).await) })
+            }
+        }
+    };
+}
+
+use service;
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt as _;
+
+    use super::*;
+
+    pub(super) async fn body<T: serde::de::DeserializeOwned>(
+        response: http::Response<ResponseBody>,
+    ) -> T {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        json::from_slice(&body).unwrap()
+    }
+
+    pub(super) fn get(uri: &str) -> http::Request<()> {
+        http::Request::get(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn query() {
+        let request = get("/apis?labelSelector=app%3Dweb&metricLabelSelector=&limit=10");
+        let query = Query::parse(&request);
+        assert_eq!(query.label_selector.as_deref(), Some("app=web"));
+        assert!(query.metric_label_selector.is_none());
+    }
+
+    #[test]
+    fn path_segments() {
+        let request = get("/apis/custom.metrics.k8s.io/v1beta2/pods/*/http_requests");
+        assert_eq!(
+            segments(&request, "/apis/custom.metrics.k8s.io").unwrap(),
+            ["v1beta2", "pods", "*", "http_requests"]
+        );
+        let request = get("/apis/custom.metrics.k8s.io/v1beta2/pods/web%2F0/requests%3F%25%23");
+        assert_eq!(
+            segments(&request, "/apis/custom.metrics.k8s.io").unwrap(),
+            ["v1beta2", "pods", "web/0", "requests?%#"]
+        );
+        assert_eq!(segments(&request, "/apis/custom"), None);
+        assert_eq!(segments(&request, "/api/v1"), None);
+    }
+
+    #[test]
+    fn decode_segment() {
+        assert_eq!(decode("web-0").as_deref(), Some("web-0"));
+        assert_eq!(decode("%C3%bc%20x").as_deref(), Some("ü x"));
+        assert_eq!(decode("100%").as_deref(), Some("100%"));
+        assert_eq!(decode("%zz+").as_deref(), Some("%zz+"));
+        assert_eq!(decode("%+F").as_deref(), Some("%+F"));
+        assert_eq!(decode("%FF"), None);
+    }
+
+    #[tokio::test]
+    async fn provider_error() {
+        let response = ProviderError::BadRequest("invalid selector".to_string()).into_response();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let status = body::<metav1::Status>(response).await;
+        assert_eq!(status.code, Some(400));
+        assert_eq!(status.reason.as_deref(), Some("BadRequest"));
+        assert_eq!(status.message.as_deref(), Some("invalid selector"));
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use constcat::concat;
+use custom_metrics::v1beta2::{MetricValue, MetricValueList};
+use custom_metrics::{AnyKind, CustomMetricInfo};
+
+use super::*;
+
+const PREFIX: &str = concat!("/apis/", CUSTOM_METRICS_API_GROUP);
+
+/// Source of custom metrics served by `CustomMetricsService`
+///
+pub trait CustomMetricsProvider: Send + Sync + 'static {
+    /// Value of `info.metric` describing object `name` (in `namespace` for namespaced resources)
+    ///
+    fn get_metric_by_name(
+        &self,
+        namespace: Option<&str>,
+        name: &str,
+        info: &CustomMetricInfo,
+        metric_selector: Option<&str>,
+    ) -> impl Future<Output = Result<MetricValue<AnyKind>, ProviderError>> + Send;
+
+    /// Values of `info.metric` describing all objects matching label `selector`
+    /// (in `namespace` for namespaced resources)
+    ///
+    fn get_metric_by_selector(
+        &self,
+        namespace: Option<&str>,
+        selector: Option<&str>,
+        info: &CustomMetricInfo,
+        metric_selector: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<MetricValue<AnyKind>>, ProviderError>> + Send;
+
+    /// All metrics served by this provider, advertised in API discovery
+    ///
+    fn list_all_metrics(&self) -> Vec<CustomMetricInfo>;
+}
+
+/// Serves `custom.metrics.k8s.io/v1beta2` from a `CustomMetricsProvider`
+///
+/// - `GET /apis/custom.metrics.k8s.io` - `APIGroup`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2` - `APIResourceList` of all metrics
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/{resource}/{name}/{metric}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/{resource}/*/{metric}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/namespaces/{namespace}/metrics/{metric}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/{resource}/{name}/{metric}`
+/// - `GET /apis/custom.metrics.k8s.io/v1beta2/{resource}/*/{metric}`
+///
+pub struct CustomMetricsService<P> {
+    provider: Arc<P>,
+}
+
+impl<P: CustomMetricsProvider> CustomMetricsService<P> {
+    pub fn new(provider: P) -> Self {
+        let provider = Arc::new(provider);
+        Self { provider }
+    }
+
+    /// Handle a single `request`
+    ///
+    pub async fn handle<B: Sync>(
+        &self,
+        request: &http::Request<B>,
+    ) -> http::Response<ResponseBody> {
+        if request.method() != http::Method::GET {
+            return method_not_allowed();
+        }
+        let Some(segments) = segments(request, PREFIX) else {
+            return not_found(request.uri().path());
+        };
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match segments.as_slice() {
+            [] => ok(&api_group(
+                CUSTOM_METRICS_API_GROUP,
+                CUSTOM_METRICS_API_VERSION,
+            )),
+            [CUSTOM_METRICS_API_VERSION] => ok(&self.api_resources()),
+            [CUSTOM_METRICS_API_VERSION, path @ ..] => self
+                .metric(path, &Query::parse(request))
+                .await
+                .unwrap_or_else(ProviderError::into_response),
+            _ => not_found(request.uri().path()),
+        }
+    }
+
+    fn api_resources(&self) -> metav1::APIResourceList {
+        let resources = self
+            .provider
+            .list_all_metrics()
+            .iter()
+            .map(CustomMetricInfo::api_resource)
+            .collect();
+        metav1::APIResourceList {
+            group_version: format!("{CUSTOM_METRICS_API_GROUP}/{CUSTOM_METRICS_API_VERSION}"),
+            resources,
+        }
+    }
+
+    async fn metric(
+        &self,
+        path: &[&str],
+        query: &Query,
+    ) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let (namespace, resource, name, metric) = match path {
+            ["namespaces", namespace, "metrics", metric] => {
+                (None, "namespaces", *namespace, *metric)
+            }
+            ["namespaces", namespace, resource, name, metric] => {
+                (Some(*namespace), *resource, *name, *metric)
+            }
+            [resource, name, metric] => (None, *resource, *name, *metric),
+            _ => return Ok(not_found(&path.join("/"))),
+        };
+
+        let info = self
+            .provider
+            .list_all_metrics()
+            .into_iter()
+            .find(|info| {
+                info.resource == resource
+                    && info.metric == metric
+                    && info.namespaced == namespace.is_some()
+            })
+            .ok_or_else(|| {
+                ProviderError::NotFound(format!(
+                    "the server could not find the metric {metric} for {resource}"
+                ))
+            })?;
+
+        let metric_selector = query.metric_label_selector.as_deref();
+        let items = if name == "*" {
+            let selector = query.label_selector.as_deref();
+            self.provider
+                .get_metric_by_selector(namespace, selector, &info, metric_selector)
+                .await?
+        } else {
+            let value = self
+                .provider
+                .get_metric_by_name(namespace, name, &info, metric_selector)
+                .await?;
+            vec![value]
+        };
+
+        let list = MetricValueList::<AnyKind> {
+            items,
+            metadata: default(),
+        };
+        Ok(ok(&list))
+    }
+}
+
+service!(CustomMetricsService, CustomMetricsProvider);
+
+#[cfg(test)]
+mod tests {
+    use tower_service::Service as _;
+
+    use super::*;
+    use crate::server::tests::{body, get};
+
+    struct Provider;
+
+    impl CustomMetricsProvider for Provider {
+        async fn get_metric_by_name(
+            &self,
+            namespace: Option<&str>,
+            name: &str,
+            info: &CustomMetricInfo,
+            metric_selector: Option<&str>,
+        ) -> Result<MetricValue<AnyKind>, ProviderError> {
+            if name == "missing" {
+                return Err(ProviderError::NotFound(format!("{name} not found")));
+            }
+            let object = corev1::ObjectReference {
+                name: Some(name.to_string()),
+                namespace: namespace.map(ToString::to_string),
+                ..default()
+            };
+            let value = if metric_selector.is_some() { 1 } else { 66 };
+            Ok(MetricValue::with_object_ref(&info.metric, &object).value(value))
+        }
+
+        async fn get_metric_by_selector(
+            &self,
+            namespace: Option<&str>,
+            selector: Option<&str>,
+            info: &CustomMetricInfo,
+            _metric_selector: Option<&str>,
+        ) -> Result<Vec<MetricValue<AnyKind>>, ProviderError> {
+            let value = match selector {
+                Some("app=web") => {
+                    self.get_metric_by_name(namespace, "web-0", info, None)
+                        .await?
+                }
+                Some(selector) => return Err(ProviderError::BadRequest(selector.to_string())),
+                None => return Ok(vec![]),
+            };
+            Ok(vec![value])
+        }
+
+        fn list_all_metrics(&self) -> Vec<CustomMetricInfo> {
+            vec![
+                CustomMetricInfo {
+                    resource: "pods".to_string(),
+                    metric: "http_requests".to_string(),
+                    namespaced: true,
+                },
+                CustomMetricInfo {
+                    resource: "namespaces".to_string(),
+                    metric: "queue_length".to_string(),
+                    namespaced: false,
+                },
+                CustomMetricInfo {
+                    resource: "nodes".to_string(),
+                    metric: "node_load1".to_string(),
+                    namespaced: false,
+                },
+            ]
+        }
+    }
+
+    async fn values(uri: &str) -> MetricValueList<corev1::Pod> {
+        let response = CustomMetricsService::new(Provider).handle(&get(uri)).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        body(response).await
+    }
+
+    async fn failure(uri: &str) -> metav1::Status {
+        let response = CustomMetricsService::new(Provider).handle(&get(uri)).await;
+        assert!(!response.status().is_success());
+        body(response).await
+    }
+
+    #[tokio::test]
+    async fn discovery() {
+        let service = CustomMetricsService::new(Provider);
+
+        let response = service.handle(&get("/apis/custom.metrics.k8s.io")).await;
+        let group = body::<metav1::APIGroup>(response).await;
+        assert_eq!(
+            group.preferred_version.unwrap().group_version,
+            "custom.metrics.k8s.io/v1beta2"
+        );
+
+        let response = service
+            .handle(&get("/apis/custom.metrics.k8s.io/v1beta2"))
+            .await;
+        let list = body::<metav1::APIResourceList>(response).await;
+        assert_eq!(
+            CustomMetricInfo::from_api_resources(&list),
+            Provider.list_all_metrics()
+        );
+    }
+
+    #[tokio::test]
+    async fn by_name() {
+        let list = values(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/web-1/http_requests",
+        )
+        .await;
+        let value = &list.items[0];
+        assert_eq!(value.described_object.name.as_deref(), Some("web-1"));
+        assert_eq!(value.described_object.namespace.as_deref(), Some("default"));
+        assert_eq!(value.metric.name, "http_requests");
+        assert_eq!(value.value, resource::Quantity("66".to_string()));
+    }
+
+    #[tokio::test]
+    async fn by_name_escaped() {
+        let list = values(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/web%3A1/http_requests",
+        )
+        .await;
+        let value = &list.items[0];
+        assert_eq!(value.described_object.name.as_deref(), Some("web:1"));
+    }
+
+    #[tokio::test]
+    async fn by_name_metric_selector() {
+        let list = values(
+            "/apis/custom.metrics.k8s.io/v1beta2/nodes/node-1/node_load1?metricLabelSelector=cpu%3D0",
+        )
+        .await;
+        let value = &list.items[0];
+        assert_eq!(value.described_object.name.as_deref(), Some("node-1"));
+        assert!(value.described_object.namespace.is_none());
+        assert_eq!(value.value, resource::Quantity("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn by_selector() {
+        let list = values(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/*/http_requests?labelSelector=app%3Dweb",
+        )
+        .await;
+        assert_eq!(list.items.len(), 1);
+        assert_eq!(
+            list.items[0].described_object.name.as_deref(),
+            Some("web-0")
+        );
+
+        let status = failure(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/*/http_requests?labelSelector=app%3Ddb",
+        )
+        .await;
+        assert_eq!(status.code, Some(400));
+    }
+
+    #[tokio::test]
+    async fn namespace_metric() {
+        let list =
+            values("/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/metrics/queue_length")
+                .await;
+        assert_eq!(
+            list.items[0].described_object.name.as_deref(),
+            Some("default")
+        );
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let status = failure(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/web-0/queue_length",
+        )
+        .await;
+        assert_eq!(status.code, Some(404));
+        assert_eq!(
+            status.message.as_deref(),
+            Some("the server could not find the metric queue_length for pods")
+        );
+
+        let status = failure(
+            "/apis/custom.metrics.k8s.io/v1beta2/namespaces/default/pods/missing/http_requests",
+        )
+        .await;
+        assert_eq!(status.message.as_deref(), Some("missing not found"));
+
+        // pods are namespaced
+        let status = failure("/apis/custom.metrics.k8s.io/v1beta2/pods/web-0/http_requests").await;
+        assert_eq!(status.code, Some(404));
+
+        let status = failure("/apis/custom.metrics.k8s.io/v1beta1").await;
+        assert_eq!(status.code, Some(404));
+
+        let status = failure("/apis/custom.metrics.k8s.io.evil/v1beta2").await;
+        assert_eq!(status.code, Some(404));
+    }
+
+    #[tokio::test]
+    async fn service() {
+        let mut service = CustomMetricsService::new(Provider);
+        let request =
+            http::Request::get("/apis/custom.metrics.k8s.io/v1beta2/nodes/node-1/node_load1")
+                .body(String::new())
+                .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let request = http::Request::post("/apis/custom.metrics.k8s.io/v1beta2")
+            .body(String::new())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
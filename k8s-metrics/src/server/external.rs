@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use constcat::concat;
+use external_metrics::v1beta1::{ExternalMetricValue, ExternalMetricValueList};
+use external_metrics::{AnyMetric, ExternalMetricInfo};
+
+use super::*;
+
+const PREFIX: &str = concat!("/apis/", EXTERNAL_METRICS_API_GROUP);
+
+/// Source of external metrics served by `ExternalMetricsService`
+///
+pub trait ExternalMetricsProvider: Send + Sync + 'static {
+    /// Values of metric `name` in `namespace`, narrowed down by label `selector` of metric series
+    ///
+    fn get_external_metric(
+        &self,
+        namespace: &str,
+        name: &str,
+        selector: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<ExternalMetricValue<AnyMetric>>, ProviderError>> + Send;
+
+    /// All metrics served by this provider, advertised in API discovery
+    ///
+    fn list_all_external_metrics(&self) -> Vec<ExternalMetricInfo>;
+}
+
+/// Serves `external.metrics.k8s.io/v1beta1` from an `ExternalMetricsProvider`
+///
+/// - `GET /apis/external.metrics.k8s.io` - `APIGroup`
+/// - `GET /apis/external.metrics.k8s.io/v1beta1` - `APIResourceList` of all metrics
+/// - `GET /apis/external.metrics.k8s.io/v1beta1/namespaces/{namespace}/{metric}`
+///
+pub struct ExternalMetricsService<P> {
+    provider: Arc<P>,
+}
+
+impl<P: ExternalMetricsProvider> ExternalMetricsService<P> {
+    pub fn new(provider: P) -> Self {
+        let provider = Arc::new(provider);
+        Self { provider }
+    }
+
+    /// Handle a single `request`
+    ///
+    pub async fn handle<B: Sync>(
+        &self,
+        request: &http::Request<B>,
+    ) -> http::Response<ResponseBody> {
+        if request.method() != http::Method::GET {
+            return method_not_allowed();
+        }
+        let Some(segments) = segments(request, PREFIX) else {
+            return not_found(request.uri().path());
+        };
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match segments.as_slice() {
+            [] => ok(&api_group(
+                EXTERNAL_METRICS_API_GROUP,
+                EXTERNAL_METRICS_API_VERSION,
+            )),
+            [EXTERNAL_METRICS_API_VERSION] => ok(&self.api_resources()),
+            [EXTERNAL_METRICS_API_VERSION, "namespaces", namespace, metric] => self
+                .metric(namespace, metric, &Query::parse(request))
+                .await
+                .unwrap_or_else(ProviderError::into_response),
+            _ => not_found(request.uri().path()),
+        }
+    }
+
+    fn api_resources(&self) -> metav1::APIResourceList {
+        let resources = self
+            .provider
+            .list_all_external_metrics()
+            .iter()
+            .map(ExternalMetricInfo::api_resource)
+            .collect();
+        metav1::APIResourceList {
+            group_version: format!("{EXTERNAL_METRICS_API_GROUP}/{EXTERNAL_METRICS_API_VERSION}"),
+            resources,
+        }
+    }
+
+    async fn metric(
+        &self,
+        namespace: &str,
+        metric: &str,
+        query: &Query,
+    ) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let served = self
+            .provider
+            .list_all_external_metrics()
+            .iter()
+            .any(|info| info.metric == metric);
+        if !served {
+            return Err(ProviderError::NotFound(format!(
+                "the server could not find the external metric {metric}"
+            )));
+        }
+
+        let items = self
+            .provider
+            .get_external_metric(namespace, metric, query.label_selector.as_deref())
+            .await?;
+        let list = ExternalMetricValueList::<AnyMetric> {
+            items,
+            metadata: default(),
+        };
+        Ok(ok(&list))
+    }
+}
+
+service!(ExternalMetricsService, ExternalMetricsProvider);
+
+#[cfg(test)]
+mod tests {
+    use external_metrics::v1beta1::ExternalMetricRequest;
+    use tower_service::Service as _;
+
+    use crate::server::tests::{body, get};
+
+    use super::*;
+
+    struct Provider;
+
+    impl ExternalMetricsProvider for Provider {
+        async fn get_external_metric(
+            &self,
+            namespace: &str,
+            name: &str,
+            selector: Option<&str>,
+        ) -> Result<Vec<ExternalMetricValue<AnyMetric>>, ProviderError> {
+            let queues = match (namespace, selector) {
+                ("messaging", None) => vec![("orders", 42), ("invoices", 0)],
+                ("messaging", Some("queue=orders")) => vec![("orders", 42)],
+                ("messaging", Some(selector)) => {
+                    return Err(ProviderError::BadRequest(format!(
+                        "unable to parse selector {selector}"
+                    )));
+                }
+                _ => vec![],
+            };
+            let values = queues
+                .into_iter()
+                .map(|(queue, messages)| {
+                    ExternalMetricValue::new(name)
+                        .label("queue", queue)
+                        .value(messages)
+                })
+                .collect();
+            Ok(values)
+        }
+
+        fn list_all_external_metrics(&self) -> Vec<ExternalMetricInfo> {
+            vec![ExternalMetricInfo {
+                metric: "rabbitmq_queue_messages_ready".to_string(),
+                namespaced: true,
+            }]
+        }
+    }
+
+    #[tokio::test]
+    async fn discovery() {
+        let service = ExternalMetricsService::new(Provider);
+
+        let response = service.handle(&get("/apis/external.metrics.k8s.io")).await;
+        let group = body::<metav1::APIGroup>(response).await;
+        assert_eq!(group.name, "external.metrics.k8s.io");
+        assert_eq!(group.versions[0].version, "v1beta1");
+
+        let response = service
+            .handle(&get("/apis/external.metrics.k8s.io/v1beta1"))
+            .await;
+        let list = body::<metav1::APIResourceList>(response).await;
+        assert_eq!(
+            ExternalMetricInfo::from_api_resources(&list),
+            Provider.list_all_external_metrics()
+        );
+        assert_eq!(list.resources[0].kind, "ExternalMetricValueList");
+    }
+
+    #[tokio::test]
+    async fn metric() {
+        let service = ExternalMetricsService::new(Provider);
+        let response = service
+            .handle(&get(
+                "/apis/external.metrics.k8s.io/v1beta1/namespaces/messaging/rabbitmq_queue_messages_ready",
+            ))
+            .await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let list = body::<ExternalMetricValueList<AnyMetric>>(response).await;
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(list.items[0].metric_name, "rabbitmq_queue_messages_ready");
+        assert_eq!(list.items[0].metric_labels["queue"], "orders");
+        assert_eq!(list.items[0].value, resource::Quantity("42".to_string()));
+    }
+
+    #[tokio::test]
+    async fn metric_selector() {
+        let mut service = ExternalMetricsService::new(Provider);
+        let request =
+            ExternalMetricRequest::<AnyMetric>::named("messaging", "rabbitmq_queue_messages_ready")
+                .selector("queue=orders");
+        let response = service.call(request.request().unwrap()).await.unwrap();
+        let status = response.status();
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let list = request.parse_response(status, &body).unwrap();
+        assert_eq!(list.items.len(), 1);
+        assert_eq!(list.items[0].metric_labels["queue"], "orders");
+
+        let request = request.selector("queue in orders");
+        let response = service.call(request.request().unwrap()).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let service = ExternalMetricsService::new(Provider);
+        let response = service
+            .handle(&get(
+                "/apis/external.metrics.k8s.io/v1beta1/namespaces/messaging/kafka_consumergroup_lag",
+            ))
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        let status = body::<metav1::Status>(response).await;
+        assert_eq!(
+            status.message.as_deref(),
+            Some("the server could not find the external metric kafka_consumergroup_lag")
+        );
+
+        let response = service
+            .handle(&get(
+                "/apis/external.metrics.k8s.io/v1beta1/rabbitmq_queue_messages_ready",
+            ))
+            .await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+}
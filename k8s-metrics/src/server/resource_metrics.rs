@@ -0,0 +1,397 @@
+use std::sync::Arc;
+
+use constcat::concat;
+
+use selector::Selector;
+use v1beta1::{NodeMetrics, PodMetrics};
+
+use super::*;
+
+const PREFIX: &str = concat!("/apis/", METRICS_API_GROUP);
+
+/// Source of resource metrics served by `ResourceMetricsService`
+///
+pub trait ResourceMetricsProvider: Send + Sync + 'static {
+    /// Metrics of all pods in `namespace`, or in all namespaces if `None`
+    ///
+    fn get_pod_metrics(
+        &self,
+        namespace: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<PodMetrics>, ProviderError>> + Send;
+
+    /// Metrics of all nodes
+    ///
+    fn get_node_metrics(
+        &self,
+    ) -> impl Future<Output = Result<Vec<NodeMetrics>, ProviderError>> + Send;
+}
+
+/// Serves `metrics.k8s.io/v1beta1` from a `ResourceMetricsProvider`, like metrics-server does
+///
+/// - `GET /apis/metrics.k8s.io` - `APIGroup`
+/// - `GET /apis/metrics.k8s.io/v1beta1` - `APIResourceList` of `nodes` and `pods`
+/// - `GET /apis/metrics.k8s.io/v1beta1/nodes`
+/// - `GET /apis/metrics.k8s.io/v1beta1/nodes/{name}`
+/// - `GET /apis/metrics.k8s.io/v1beta1/pods`
+/// - `GET /apis/metrics.k8s.io/v1beta1/namespaces/{namespace}/pods`
+/// - `GET /apis/metrics.k8s.io/v1beta1/namespaces/{namespace}/pods/{name}`
+///
+/// Lists are filtered by `labelSelector` matched against object labels.
+///
+pub struct ResourceMetricsService<P> {
+    provider: Arc<P>,
+}
+
+impl<P: ResourceMetricsProvider> ResourceMetricsService<P> {
+    pub fn new(provider: P) -> Self {
+        let provider = Arc::new(provider);
+        Self { provider }
+    }
+
+    /// Handle a single `request`
+    ///
+    pub async fn handle<B: Sync>(
+        &self,
+        request: &http::Request<B>,
+    ) -> http::Response<ResponseBody> {
+        if request.method() != http::Method::GET {
+            return method_not_allowed();
+        }
+        let Some(segments) = segments(request, PREFIX) else {
+            return not_found(request.uri().path());
+        };
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let response = match segments.as_slice() {
+            [] => Ok(ok(&api_group(METRICS_API_GROUP, METRICS_API_VERSION))),
+            [METRICS_API_VERSION] => Ok(ok(&api_resources())),
+            [METRICS_API_VERSION, "nodes"] => self.nodes(&Query::parse(request)).await,
+            [METRICS_API_VERSION, "nodes", name] => self.node(name).await,
+            [METRICS_API_VERSION, "pods"] => self.pods(None, &Query::parse(request)).await,
+            [METRICS_API_VERSION, "namespaces", namespace, "pods"] => {
+                self.pods(Some(namespace), &Query::parse(request)).await
+            }
+            [METRICS_API_VERSION, "namespaces", namespace, "pods", name] => {
+                self.pod(namespace, name).await
+            }
+            _ => Ok(not_found(request.uri().path())),
+        };
+        response.unwrap_or_else(ProviderError::into_response)
+    }
+
+    async fn nodes(&self, query: &Query) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let selector = selector(query)?;
+        let items = self.provider.get_node_metrics().await?;
+        Ok(ok(&list(items, &selector)))
+    }
+
+    async fn node(&self, name: &str) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let node = self
+            .provider
+            .get_node_metrics()
+            .await?
+            .into_iter()
+            .find(|node| node.metadata.name.as_deref() == Some(name));
+        Ok(node.map_or_else(
+            || object_not_found::<NodeMetrics>(name),
+            |node| object(&node),
+        ))
+    }
+
+    async fn pods(
+        &self,
+        namespace: Option<&str>,
+        query: &Query,
+    ) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let selector = selector(query)?;
+        let items = self.provider.get_pod_metrics(namespace).await?;
+        Ok(ok(&list(items, &selector)))
+    }
+
+    async fn pod(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<http::Response<ResponseBody>, ProviderError> {
+        let pod = self
+            .provider
+            .get_pod_metrics(Some(namespace))
+            .await?
+            .into_iter()
+            .find(|pod| pod.metadata.name.as_deref() == Some(name));
+        Ok(pod.map_or_else(|| object_not_found::<PodMetrics>(name), |pod| object(&pod)))
+    }
+}
+
+service!(ResourceMetricsService, ResourceMetricsProvider);
+
+fn api_resources() -> metav1::APIResourceList {
+    let resource = |name: &str, kind: &str, namespaced| metav1::APIResource {
+        name: name.to_string(),
+        namespaced,
+        kind: kind.to_string(),
+        verbs: vec!["get".to_string(), "list".to_string()],
+        ..default()
+    };
+    metav1::APIResourceList {
+        group_version: format!("{METRICS_API_GROUP}/{METRICS_API_VERSION}"),
+        resources: vec![
+            resource("nodes", "NodeMetrics", false),
+            resource("pods", "PodMetrics", true),
+        ],
+    }
+}
+
+fn selector(query: &Query) -> Result<Selector, ProviderError> {
+    query
+        .label_selector
+        .as_deref()
+        .unwrap_or_default()
+        .parse()
+        .map_err(|err: selector::SelectorParseError| ProviderError::BadRequest(err.to_string()))
+}
+
+fn list<K>(items: Vec<K>, selector: &Selector) -> k8s::List<K>
+where
+    K: k8s::ListableResource + k8s::Metadata<Ty = metav1::ObjectMeta>,
+{
+    let items = items
+        .into_iter()
+        .filter(|item| selector.matches_object(item))
+        .collect();
+    k8s::List {
+        items,
+        metadata: default(),
+    }
+}
+
+/// Single object response, tagged with its `kind` and `apiVersion`
+///
+fn object<K: k8s::Resource + Serialize>(object: &K) -> http::Response<ResponseBody> {
+    let mut value = json::to_value(object).expect("metrics types serialize to JSON");
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert("kind".to_string(), K::KIND.into());
+        fields.insert("apiVersion".to_string(), K::API_VERSION.into());
+    }
+    ok(&value)
+}
+
+/// `NotFound` status for object `name`, e.g. `podmetrics.metrics.k8s.io "web-0" not found`
+///
+fn object_not_found<K: k8s::Resource>(name: &str) -> http::Response<ResponseBody> {
+    let code = http::StatusCode::NOT_FOUND;
+    let status = metav1::Status {
+        code: Some(code.as_u16().into()),
+        details: Some(metav1::StatusDetails {
+            group: Some(K::GROUP.to_string()),
+            kind: Some(K::URL_PATH_SEGMENT.to_string()),
+            name: Some(name.to_string()),
+            ..default()
+        }),
+        message: Some(format!(
+            "{}.{} \"{name}\" not found",
+            K::KIND.to_lowercase(),
+            K::GROUP
+        )),
+        reason: Some("NotFound".to_string()),
+        status: Some("Failure".to_string()),
+        ..default()
+    };
+    respond(code, &status)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tower_service::Service as _;
+
+    use v1beta1::{Container, Usage};
+
+    use crate::server::tests::{body, get};
+
+    use super::*;
+
+    struct Provider;
+
+    fn metadata(namespace: Option<&str>, name: &str, app: &str) -> metav1::ObjectMeta {
+        metav1::ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: namespace.map(ToString::to_string),
+            labels: Some(BTreeMap::from([("app".to_string(), app.to_string())])),
+            ..default()
+        }
+    }
+
+    fn usage(cpu: &str, memory: &str) -> Usage {
+        Usage {
+            cpu: resource::Quantity(cpu.to_string()),
+            memory: resource::Quantity(memory.to_string()),
+        }
+    }
+
+    fn pod(namespace: &str, name: &str, app: &str) -> PodMetrics {
+        PodMetrics {
+            metadata: metadata(Some(namespace), name, app),
+            containers: vec![Container {
+                name: app.to_string(),
+                usage: usage("250m", "64Mi"),
+            }],
+            window: time::Duration::from_secs(15),
+            ..default()
+        }
+    }
+
+    impl ResourceMetricsProvider for Provider {
+        async fn get_pod_metrics(
+            &self,
+            namespace: Option<&str>,
+        ) -> Result<Vec<PodMetrics>, ProviderError> {
+            let pods = [
+                pod("default", "web-0", "web"),
+                pod("default", "db-0", "db"),
+                pod("kube-system", "coredns", "dns"),
+            ];
+            let pods = pods
+                .into_iter()
+                .filter(|pod| namespace.is_none() || pod.metadata.namespace.as_deref() == namespace)
+                .collect();
+            Ok(pods)
+        }
+
+        async fn get_node_metrics(&self) -> Result<Vec<NodeMetrics>, ProviderError> {
+            let node = NodeMetrics {
+                metadata: metadata(None, "node-1", "worker"),
+                usage: usage("1500m", "2Gi"),
+                window: time::Duration::from_secs(15),
+                ..default()
+            };
+            Ok(vec![node])
+        }
+    }
+
+    async fn handle(uri: &str) -> http::Response<ResponseBody> {
+        ResourceMetricsService::new(Provider)
+            .handle(&get(uri))
+            .await
+    }
+
+    fn names<K: k8s::ListableResource + k8s::Metadata<Ty = metav1::ObjectMeta>>(
+        list: &k8s::List<K>,
+    ) -> Vec<&str> {
+        list.items
+            .iter()
+            .filter_map(|item| item.metadata().name.as_deref())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn discovery() {
+        let group = body::<metav1::APIGroup>(handle("/apis/metrics.k8s.io").await).await;
+        assert_eq!(
+            group.preferred_version.unwrap().group_version,
+            "metrics.k8s.io/v1beta1"
+        );
+
+        let list =
+            body::<metav1::APIResourceList>(handle("/apis/metrics.k8s.io/v1beta1").await).await;
+        let resources = list
+            .resources
+            .iter()
+            .map(|resource| (resource.name.as_str(), resource.namespaced))
+            .collect::<Vec<_>>();
+        assert_eq!(resources, [("nodes", false), ("pods", true)]);
+    }
+
+    #[tokio::test]
+    async fn list_pods() {
+        let response = handle("/apis/metrics.k8s.io/v1beta1/namespaces/default/pods").await;
+        let list = body::<json::Value>(response).await;
+        assert_eq!(list["kind"], "PodMetricsList");
+        assert_eq!(list["apiVersion"], "metrics.k8s.io/v1beta1");
+
+        let list = json::from_value::<k8s::List<PodMetrics>>(list).unwrap();
+        assert_eq!(names(&list), ["web-0", "db-0"]);
+
+        let response = handle("/apis/metrics.k8s.io/v1beta1/pods").await;
+        let list = body::<k8s::List<PodMetrics>>(response).await;
+        assert_eq!(names(&list), ["web-0", "db-0", "coredns"]);
+    }
+
+    #[tokio::test]
+    async fn label_selector() {
+        let response =
+            handle("/apis/metrics.k8s.io/v1beta1/pods?labelSelector=app+in+%28web%2Cdns%29").await;
+        let list = body::<k8s::List<PodMetrics>>(response).await;
+        assert_eq!(names(&list), ["web-0", "coredns"]);
+
+        let response =
+            handle("/apis/metrics.k8s.io/v1beta1/nodes?labelSelector=app%3Dmaster").await;
+        let list = body::<k8s::List<NodeMetrics>>(response).await;
+        assert_eq!(list.items, []);
+
+        let response = handle("/apis/metrics.k8s.io/v1beta1/pods?labelSelector=app+in+web").await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_objects() {
+        let response = handle("/apis/metrics.k8s.io/v1beta1/namespaces/default/pods/db-0").await;
+        let pod = body::<json::Value>(response).await;
+        assert_eq!(pod["kind"], "PodMetrics");
+        assert_eq!(pod["window"], "15s");
+        assert_eq!(pod["containers"][0]["usage"]["cpu"], "250m");
+
+        let response = handle("/apis/metrics.k8s.io/v1beta1/nodes/node-1").await;
+        let node = body::<NodeMetrics>(response).await;
+        assert_eq!(node.usage.cpu, resource::Quantity("1500m".to_string()));
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let response =
+            handle("/apis/metrics.k8s.io/v1beta1/namespaces/kube-system/pods/web-0").await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        let status = body::<metav1::Status>(response).await;
+        assert_eq!(
+            status.message.as_deref(),
+            Some("podmetrics.metrics.k8s.io \"web-0\" not found")
+        );
+        let details = status.details.unwrap();
+        assert_eq!(details.name.as_deref(), Some("web-0"));
+        assert_eq!(details.kind.as_deref(), Some("pods"));
+
+        let response = handle("/apis/metrics.k8s.io/v1beta1/namespaces/default/services").await;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn kube_api() {
+        let service = ResourceMetricsService::new(Provider);
+        let client = kube::Client::new(service, "default");
+
+        let pods = kube::Api::<PodMetrics>::default_namespaced(client.clone());
+        let pod = pods.get("web-0").await.unwrap();
+        assert_eq!(pod.cpu().unwrap(), Cpu::from_millicores(250));
+
+        let list = pods.list(&default()).await.unwrap();
+        assert_eq!(list.items.len(), 2);
+
+        let nodes = kube::Api::<NodeMetrics>::all(client);
+        let params = kube::api::ListParams::default().labels("app=worker");
+        assert_eq!(nodes.list(&params).await.unwrap().items.len(), 1);
+
+        let err = pods.get("missing").await.unwrap_err();
+        assert!(matches!(err, kube::Error::Api(status) if status.code == 404));
+    }
+
+    #[tokio::test]
+    async fn service() {
+        let mut service = ResourceMetricsService::new(Provider);
+        let request = http::Request::delete("/apis/metrics.k8s.io/v1beta1/nodes/node-1")
+            .body(String::new())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,592 @@
+//! `kubectl top` style tables
+//!
+//! Renders `PodMetrics` and `NodeMetrics` exactly the way `kubectl top pod`,
+//! `kubectl top pod --containers` and `kubectl top node` do.
+//!
+
+use std::cmp;
+use std::fmt;
+use std::str;
+
+use super::*;
+
+use v1beta1::{NodeMetrics, PodMetrics};
+
+const MIN_WIDTH: usize = 6;
+const PADDING: usize = 3;
+const UNKNOWN: &str = "<unknown>";
+
+/// Sort order of `kubectl top` tables
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    /// By namespace (when shown) and name, ascending
+    ///
+    #[default]
+    Name,
+    /// By CPU usage, descending
+    ///
+    Cpu,
+    /// By memory usage, descending
+    ///
+    Memory,
+}
+
+impl str::FromStr for SortBy {
+    type Err = InvalidSortBy;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "" | "name" => Ok(Self::Name),
+            "cpu" => Ok(Self::Cpu),
+            "memory" => Ok(Self::Memory),
+            other => Err(InvalidSortBy(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("--sort-by accepts only cpu or memory, got '{0}'")]
+pub struct InvalidSortBy(String);
+
+/// Which node resources the `CPU(%)` and `MEMORY(%)` columns are relative to
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NodeResources {
+    /// `status.allocatable`, what `kubectl top node` shows by default
+    ///
+    #[default]
+    Allocatable,
+    /// `status.capacity`, what `kubectl top node --show-capacity` shows
+    ///
+    Capacity,
+}
+
+/// `kubectl top pod` table
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TopPods {
+    containers: bool,
+    namespace: bool,
+    no_headers: bool,
+    sort_by: SortBy,
+}
+
+impl TopPods {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Print usage of individual containers, like `--containers`
+    ///
+    pub fn containers(self, containers: bool) -> Self {
+        Self { containers, ..self }
+    }
+
+    /// Print `NAMESPACE` column, like `--all-namespaces`
+    ///
+    pub fn namespace(self, namespace: bool) -> Self {
+        Self { namespace, ..self }
+    }
+
+    /// Omit the header line, like `--no-headers`
+    ///
+    pub fn no_headers(self, no_headers: bool) -> Self {
+        Self { no_headers, ..self }
+    }
+
+    /// Sort rows, like `--sort-by`
+    ///
+    pub fn sort_by(self, sort_by: SortBy) -> Self {
+        Self { sort_by, ..self }
+    }
+
+    /// Render `pods` as a table
+    ///
+    pub fn render(&self, pods: &[PodMetrics]) -> Result<String, QuantityParseError> {
+        let mut table = Table::default();
+        if pods.is_empty() {
+            return Ok(table.to_string());
+        }
+
+        if !self.no_headers {
+            let mut header = vec![];
+            if self.namespace {
+                header.push("NAMESPACE");
+            }
+            if self.containers {
+                header.push("POD");
+            }
+            header.extend(["NAME", "CPU(cores)", "MEMORY(bytes)"]);
+            table.push(header);
+        }
+
+        let mut rows = pods
+            .iter()
+            .map(|pod| {
+                let usage = pod.usage()?;
+                Ok((pod, usage.cpu()?, usage.memory()?))
+            })
+            .collect::<Result<Vec<_>, QuantityParseError>>()?;
+
+        rows.sort_by(
+            |(a, a_cpu, a_memory), (b, b_cpu, b_memory)| match self.sort_by {
+                SortBy::Name if self.namespace => {
+                    (namespace(*a), name(*a)).cmp(&(namespace(*b), name(*b)))
+                }
+                SortBy::Name => name(*a).cmp(name(*b)),
+                SortBy::Cpu => b_cpu.millicores().cmp(&a_cpu.millicores()),
+                SortBy::Memory => b_memory.cmp(a_memory),
+            },
+        );
+
+        for (pod, cpu, memory) in rows {
+            let mut prefix = vec![];
+            if self.namespace {
+                prefix.push(namespace(pod).to_string());
+            }
+
+            if self.containers {
+                let mut containers = pod
+                    .containers
+                    .iter()
+                    .map(|container| Ok((container, container.cpu()?, container.memory()?)))
+                    .collect::<Result<Vec<_>, QuantityParseError>>()?;
+                containers.sort_by(|(a, a_cpu, a_memory), (b, b_cpu, b_memory)| {
+                    match self.sort_by {
+                        SortBy::Name => a.name.cmp(&b.name),
+                        SortBy::Cpu => b_cpu.millicores().cmp(&a_cpu.millicores()),
+                        SortBy::Memory => b_memory.cmp(a_memory),
+                    }
+                });
+
+                for (container, cpu, memory) in containers {
+                    let mut row = prefix.clone();
+                    row.push(name(pod).to_string());
+                    row.extend([container.name.clone(), cpu.to_string(), memory.to_string()]);
+                    table.push(row);
+                }
+            } else {
+                let mut row = prefix;
+                row.extend([name(pod).to_string(), cpu.to_string(), memory.to_string()]);
+                table.push(row);
+            }
+        }
+
+        Ok(table.to_string())
+    }
+}
+
+/// `kubectl top node` table
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TopNodes {
+    no_headers: bool,
+    sort_by: SortBy,
+    resources: NodeResources,
+}
+
+impl TopNodes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Omit the header line, like `--no-headers`
+    ///
+    pub fn no_headers(self, no_headers: bool) -> Self {
+        Self { no_headers, ..self }
+    }
+
+    /// Sort rows, like `--sort-by`
+    ///
+    pub fn sort_by(self, sort_by: SortBy) -> Self {
+        Self { sort_by, ..self }
+    }
+
+    /// Node resources percentages are relative to, like `--show-capacity`
+    ///
+    pub fn resources(self, resources: NodeResources) -> Self {
+        Self { resources, ..self }
+    }
+
+    /// Render `metrics` as a table
+    ///
+    /// When `nodes` are given, `CPU(%)` and `MEMORY(%)` are relative to their resources,
+    /// and nodes without metrics are listed with `<unknown>` usage.
+    ///
+    pub fn render(
+        &self,
+        metrics: &[NodeMetrics],
+        nodes: &[corev1::Node],
+    ) -> Result<String, QuantityParseError> {
+        let mut table = Table::default();
+        if metrics.is_empty() && nodes.is_empty() {
+            return Ok(table.to_string());
+        }
+
+        // Like kubectl, percentages are left out without nodes to relate usage to
+        let percentages = !nodes.is_empty();
+        if !self.no_headers {
+            if percentages {
+                table.push(vec![
+                    "NAME",
+                    "CPU(cores)",
+                    "CPU(%)",
+                    "MEMORY(bytes)",
+                    "MEMORY(%)",
+                ]);
+            } else {
+                table.push(vec!["NAME", "CPU(cores)", "MEMORY(bytes)"]);
+            }
+        }
+
+        let mut rows = metrics
+            .iter()
+            .map(|node| Ok((node, node.cpu()?, node.memory()?)))
+            .collect::<Result<Vec<_>, QuantityParseError>>()?;
+
+        rows.sort_by(
+            |(a, a_cpu, a_memory), (b, b_cpu, b_memory)| match self.sort_by {
+                SortBy::Name => name(*a).cmp(name(*b)),
+                SortBy::Cpu => b_cpu.millicores().cmp(&a_cpu.millicores()),
+                SortBy::Memory => b_memory.cmp(a_memory),
+            },
+        );
+
+        for (node, cpu, memory) in rows {
+            let row = if percentages {
+                let (cpu_percent, memory_percent) = match self.available(nodes, name(node))? {
+                    Some((available_cpu, available_memory)) => (
+                        percent(cpu.millicores(), available_cpu.millicores()),
+                        percent(memory.bytes(), available_memory.bytes()),
+                    ),
+                    None => (UNKNOWN.to_string(), UNKNOWN.to_string()),
+                };
+                vec![
+                    name(node).to_string(),
+                    cpu.to_string(),
+                    cpu_percent,
+                    memory.to_string(),
+                    memory_percent,
+                ]
+            } else {
+                vec![name(node).to_string(), cpu.to_string(), memory.to_string()]
+            };
+            table.push(row);
+        }
+
+        let missing = nodes
+            .iter()
+            .map(name)
+            .filter(|node| metrics.iter().all(|metrics| name(metrics) != *node));
+        for node in missing {
+            table.push(vec![node, UNKNOWN, UNKNOWN, UNKNOWN, UNKNOWN]);
+        }
+
+        Ok(table.to_string())
+    }
+
+    fn available(
+        &self,
+        nodes: &[corev1::Node],
+        node: &str,
+    ) -> Result<Option<(Cpu, Memory)>, QuantityParseError> {
+        let Some(status) = nodes
+            .iter()
+            .find(|item| name(*item) == node)
+            .and_then(|node| node.status.as_ref())
+        else {
+            return Ok(None);
+        };
+
+        let resources = match self.resources {
+            NodeResources::Allocatable => status.allocatable.as_ref(),
+            NodeResources::Capacity => status.capacity.as_ref(),
+        };
+
+        let Some((cpu, memory)) =
+            resources.and_then(|resources| resources.get("cpu").zip(resources.get("memory")))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some((Cpu::try_from(cpu)?, Memory::try_from(memory)?)))
+    }
+}
+
+fn name<K>(object: &K) -> &str
+where
+    K: k8s::Metadata<Ty = metav1::ObjectMeta>,
+{
+    object.metadata().name.as_deref().unwrap_or_default()
+}
+
+fn namespace<K>(object: &K) -> &str
+where
+    K: k8s::Metadata<Ty = metav1::ObjectMeta>,
+{
+    object.metadata().namespace.as_deref().unwrap_or_default()
+}
+
+/// Percentage truncated to integer, the way `kubectl top` computes it
+///
+/// `<unknown>` when nothing is available, e.g. a node reporting zero allocatable memory.
+///
+fn percent(usage: i64, available: i64) -> String {
+    if available <= 0 {
+        return UNKNOWN.to_string();
+    }
+    let percent = usage as f64 / available as f64 * 100.0;
+    format!("{}%", percent as i64)
+}
+
+/// Table aligned like Go's `text/tabwriter` configured by `kubectl`
+///
+/// Every cell, including the last one, is padded to the widest cell of its column
+/// plus three spaces, with a minimum column width of six.
+///
+#[derive(Debug, Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn push<T: ToString>(&mut self, row: Vec<T>) {
+        let row = row.iter().map(ToString::to_string).collect();
+        self.rows.push(row);
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths = vec![];
+        for row in &self.rows {
+            for (column, cell) in row.iter().enumerate() {
+                let width = cmp::max(cell.chars().count() + PADDING, MIN_WIDTH);
+                match widths.get_mut(column) {
+                    Some(current) => *current = cmp::max(*current, width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        widths
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths = self.widths();
+        for row in &self.rows {
+            for (cell, width) in row.iter().zip(&widths) {
+                write!(f, "{cell:width$}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    use v1beta1::{Container, Usage};
+
+    fn usage(cpu: &str, memory: &str) -> Usage {
+        Usage {
+            cpu: resource::Quantity(cpu.to_string()),
+            memory: resource::Quantity(memory.to_string()),
+        }
+    }
+
+    fn pod(namespace: &str, name: &str, containers: &[(&str, &str, &str)]) -> PodMetrics {
+        PodMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..default()
+            },
+            containers: containers
+                .iter()
+                .map(|(name, cpu, memory)| Container {
+                    name: name.to_string(),
+                    usage: usage(cpu, memory),
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    fn node_metrics(name: &str, cpu: &str, memory: &str) -> NodeMetrics {
+        NodeMetrics {
+            metadata: metav1::ObjectMeta {
+                name: Some(name.to_string()),
+                ..default()
+            },
+            usage: usage(cpu, memory),
+            ..default()
+        }
+    }
+
+    fn node(name: &str, cpu: &str, memory: &str) -> corev1::Node {
+        let resources = BTreeMap::from([
+            ("cpu".to_string(), resource::Quantity(cpu.to_string())),
+            ("memory".to_string(), resource::Quantity(memory.to_string())),
+        ]);
+        corev1::Node {
+            metadata: metav1::ObjectMeta {
+                name: Some(name.to_string()),
+                ..default()
+            },
+            status: Some(corev1::NodeStatus {
+                allocatable: Some(resources.clone()),
+                capacity: Some(resources),
+                ..default()
+            }),
+            ..default()
+        }
+    }
+
+    fn trim_lines(table: &str) -> String {
+        table
+            .lines()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+
+    fn pods() -> Vec<PodMetrics> {
+        vec![
+            pod(
+                "kube-system",
+                "metrics-server-6db985556d-nqbdz",
+                &[("metrics-server", "6082165n", "22272Ki")],
+            ),
+            pod(
+                "default",
+                "web-0",
+                &[("nginx", "250m", "64Mi"), ("envoy", "1500u", "128Mi")],
+            ),
+        ]
+    }
+
+    #[test]
+    fn top_pod() {
+        let table = TopPods::new().render(&pods()).unwrap();
+        let expected = "\
+NAME                              CPU(cores)   MEMORY(bytes)
+metrics-server-6db985556d-nqbdz   7m           21Mi
+web-0                             252m         192Mi
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn top_pod_all_namespaces_sorted_by_cpu() {
+        let table = TopPods::new()
+            .namespace(true)
+            .sort_by(SortBy::Cpu)
+            .render(&pods())
+            .unwrap();
+        let expected = "\
+NAMESPACE     NAME                              CPU(cores)   MEMORY(bytes)
+default       web-0                             252m         192Mi
+kube-system   metrics-server-6db985556d-nqbdz   7m           21Mi
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn top_pod_containers() {
+        let table = TopPods::new()
+            .containers(true)
+            .no_headers(true)
+            .sort_by(SortBy::Memory)
+            .render(&pods())
+            .unwrap();
+        let expected = "\
+web-0                             envoy            2m     128Mi
+web-0                             nginx            250m   64Mi
+metrics-server-6db985556d-nqbdz   metrics-server   7m     21Mi
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn top_pod_empty() {
+        assert_eq!(TopPods::new().render(&[]).unwrap(), "");
+    }
+
+    #[test]
+    fn top_pod_invalid() {
+        let pods = [pod("default", "broken", &[("app", "1x", "1Mi")])];
+        let err = TopPods::new().render(&pods).unwrap_err();
+        assert!(err.to_string().contains("1x"));
+    }
+
+    #[test]
+    fn top_node() {
+        let metrics = [
+            node_metrics("worker", "1500m", "3Gi"),
+            node_metrics("control-plane", "196382978n", "1848836Ki"),
+        ];
+        let nodes = [
+            node("control-plane", "4", "8Gi"),
+            node("worker", "8", "16Gi"),
+            node("pending", "2", "2Gi"),
+        ];
+        let table = TopNodes::new().render(&metrics, &nodes).unwrap();
+        let expected = "\
+NAME            CPU(cores)   CPU(%)      MEMORY(bytes)   MEMORY(%)
+control-plane   197m         4%          1805Mi          22%
+worker          1500m        18%         3072Mi          18%
+pending         <unknown>    <unknown>   <unknown>       <unknown>
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn top_node_without_nodes() {
+        let metrics = [
+            node_metrics("a", "100m", "1Gi"),
+            node_metrics("b", "200m", "512Mi"),
+        ];
+        let table = TopNodes::new()
+            .sort_by(SortBy::Cpu)
+            .render(&metrics, &[])
+            .unwrap();
+        let expected = "\
+NAME   CPU(cores)   MEMORY(bytes)
+b      200m         512Mi
+a      100m         1024Mi
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn top_node_without_resources() {
+        let metrics = [
+            node_metrics("bare", "100m", "1Gi"),
+            node_metrics("empty", "200m", "512Mi"),
+            node_metrics("unlisted", "300m", "2Gi"),
+        ];
+        let mut bare = node("bare", "4", "8Gi");
+        bare.status = None;
+        let nodes = [bare, node("empty", "0", "0")];
+        let table = TopNodes::new().render(&metrics, &nodes).unwrap();
+        let expected = "\
+NAME       CPU(cores)   CPU(%)      MEMORY(bytes)   MEMORY(%)
+bare       100m         <unknown>   1024Mi          <unknown>
+empty      200m         <unknown>   512Mi           <unknown>
+unlisted   300m         <unknown>   2048Mi          <unknown>
+";
+        assert_eq!(trim_lines(&table), expected);
+    }
+
+    #[test]
+    fn sort_by() {
+        assert_eq!("cpu".parse::<SortBy>().unwrap(), SortBy::Cpu);
+        assert_eq!("memory".parse::<SortBy>().unwrap(), SortBy::Memory);
+        assert_eq!("".parse::<SortBy>().unwrap(), SortBy::Name);
+        "disk".parse::<SortBy>().unwrap_err();
+    }
+}
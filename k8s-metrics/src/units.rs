@@ -0,0 +1,286 @@
+use std::fmt;
+use std::iter;
+use std::ops;
+
+use super::*;
+
+const NANOS_PER_CORE: i64 = 1_000_000_000;
+const NANOS_PER_MILLICORE: i64 = 1_000_000;
+const KIB: i64 = 1024;
+const MIB: i64 = 1024 * KIB;
+const GIB: i64 = 1024 * MIB;
+
+/// CPU usage, stored as whole nanocores
+///
+/// Displayed in millicores like `kubectl top` does, e.g. `250m`. Conversions and
+/// arithmetic saturate at the bounds of `i64` nanocores instead of overflowing.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cpu(i64);
+
+/// Memory usage, stored as whole bytes
+///
+/// Displayed in mebibytes like `kubectl top` does, e.g. `128Mi`. Conversions and
+/// arithmetic saturate at the bounds of `i64` bytes instead of overflowing.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Memory(i64);
+
+impl Cpu {
+    pub const fn from_nanocores(nanocores: i64) -> Self {
+        Self(nanocores)
+    }
+
+    pub const fn from_millicores(millicores: i64) -> Self {
+        Self(millicores.saturating_mul(NANOS_PER_MILLICORE))
+    }
+
+    pub fn from_cores(cores: f64) -> Self {
+        Self((cores * NANOS_PER_CORE as f64).round() as i64)
+    }
+
+    pub const fn nanocores(&self) -> i64 {
+        self.0
+    }
+
+    /// Millicores rounded up (away from zero), the way Kubernetes rounds `MilliValue()`
+    ///
+    pub const fn millicores(&self) -> i64 {
+        let millicores = self.0 / NANOS_PER_MILLICORE;
+        millicores + (self.0 % NANOS_PER_MILLICORE).signum()
+    }
+
+    pub fn cores(&self) -> f64 {
+        self.0 as f64 / NANOS_PER_CORE as f64
+    }
+}
+
+impl Memory {
+    pub const fn from_bytes(bytes: i64) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn from_kib(kib: i64) -> Self {
+        Self(kib.saturating_mul(KIB))
+    }
+
+    pub const fn from_mib(mib: i64) -> Self {
+        Self(mib.saturating_mul(MIB))
+    }
+
+    pub const fn from_gib(gib: i64) -> Self {
+        Self(gib.saturating_mul(GIB))
+    }
+
+    pub const fn bytes(&self) -> i64 {
+        self.0
+    }
+
+    pub fn kib(&self) -> f64 {
+        self.0 as f64 / KIB as f64
+    }
+
+    pub fn mib(&self) -> f64 {
+        self.0 as f64 / MIB as f64
+    }
+
+    pub fn gib(&self) -> f64 {
+        self.0 as f64 / GIB as f64
+    }
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}m", self.millicores())
+    }
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}Mi", self.0 / MIB)
+    }
+}
+
+impl TryFrom<&resource::Quantity> for Cpu {
+    type Error = QuantityParseError;
+
+    fn try_from(quantity: &resource::Quantity) -> Result<Self, Self::Error> {
+        quantity
+            .to_parsed()?
+            .to_scaled_i64(-9)
+            .map(Self)
+            .ok_or_else(|| QuantityParseError::new(&quantity.0))
+    }
+}
+
+impl TryFrom<&resource::Quantity> for Memory {
+    type Error = QuantityParseError;
+
+    fn try_from(quantity: &resource::Quantity) -> Result<Self, Self::Error> {
+        quantity.to_memory().map(Self)
+    }
+}
+
+impl From<Cpu> for ParsedQuantity {
+    fn from(cpu: Cpu) -> Self {
+        Self::new(cpu.0, -9, Format::DecimalSI)
+    }
+}
+
+impl From<Memory> for ParsedQuantity {
+    fn from(memory: Memory) -> Self {
+        Self::new(memory.0, 0, Format::BinarySI)
+    }
+}
+
+impl From<Cpu> for resource::Quantity {
+    fn from(cpu: Cpu) -> Self {
+        ParsedQuantity::from(cpu).into()
+    }
+}
+
+impl From<Memory> for resource::Quantity {
+    fn from(memory: Memory) -> Self {
+        ParsedQuantity::from(memory).into()
+    }
+}
+
+macro_rules! arithmetic {
+    ($t:ty) => {
+        impl ops::Add for $t {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self::Output {
+                Self(self.0.saturating_add(other.0))
+            }
+        }
+
+        impl ops::Sub for $t {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self::Output {
+                Self(self.0.saturating_sub(other.0))
+            }
+        }
+
+        impl ops::AddAssign for $t {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl ops::SubAssign for $t {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl iter::Sum for $t {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::default(), |sum, item| sum + item)
+            }
+        }
+
+        impl<'a> iter::Sum<&'a Self> for $t {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.copied().sum()
+            }
+        }
+    };
+}
+
+arithmetic!(Cpu);
+arithmetic!(Memory);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantity(v: &str) -> resource::Quantity {
+        resource::Quantity(v.to_string())
+    }
+
+    #[test]
+    fn cpu_units() {
+        let cpu = Cpu::from_millicores(250);
+        assert_eq!(cpu.nanocores(), 250_000_000);
+        assert_eq!(cpu.millicores(), 250);
+        assert_eq!(cpu.cores(), 0.25);
+        assert_eq!(Cpu::from_cores(0.25), cpu);
+    }
+
+    #[test]
+    fn cpu_millicores_round_up() {
+        assert_eq!(Cpu::from_nanocores(6082165).millicores(), 7);
+        assert_eq!(Cpu::from_nanocores(-1).millicores(), -1);
+        assert_eq!(Cpu::from_nanocores(0).millicores(), 0);
+    }
+
+    #[test]
+    fn memory_units() {
+        let memory = Memory::from_mib(128);
+        assert_eq!(memory.bytes(), 134217728);
+        assert_eq!(memory.kib(), 131072.0);
+        assert_eq!(memory.mib(), 128.0);
+        assert_eq!(memory.gib(), 0.125);
+        assert_eq!(Memory::from_kib(131072), memory);
+        assert_eq!(Memory::from_gib(1), Memory::from_mib(1024));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Cpu::from_nanocores(196382978).to_string(), "197m");
+        assert_eq!(Memory::from_kib(1848836).to_string(), "1805Mi");
+    }
+
+    #[test]
+    fn from_quantity() {
+        let cpu = Cpu::try_from(&quantity("1.5")).unwrap();
+        assert_eq!(cpu, Cpu::from_millicores(1500));
+        let memory = Memory::try_from(&quantity("1.5Gi")).unwrap();
+        assert_eq!(memory, Memory::from_mib(1536));
+        Cpu::try_from(&quantity("1.5Gb")).unwrap_err();
+    }
+
+    #[test]
+    fn to_quantity() {
+        assert_eq!(
+            resource::Quantity::from(Cpu::from_millicores(250)).0,
+            "250m"
+        );
+        assert_eq!(resource::Quantity::from(Cpu::from_nanocores(257)).0, "257n");
+        assert_eq!(resource::Quantity::from(Memory::from_mib(128)).0, "128Mi");
+        assert_eq!(resource::Quantity::from(Memory::from_bytes(1000)).0, "1k");
+    }
+
+    #[test]
+    fn sum() {
+        let cpu = [Cpu::from_millicores(100), Cpu::from_millicores(150)]
+            .into_iter()
+            .sum::<Cpu>();
+        assert_eq!(cpu, Cpu::from_millicores(250));
+        let memory = [Memory::from_mib(1), Memory::from_mib(2)]
+            .iter()
+            .sum::<Memory>();
+        assert_eq!(memory - Memory::from_mib(1), Memory::from_mib(2));
+    }
+
+    #[test]
+    fn saturating() {
+        assert_eq!(Cpu::from_millicores(i64::MAX).nanocores(), i64::MAX);
+        assert_eq!(Cpu::from_millicores(i64::MIN).nanocores(), i64::MIN);
+        assert_eq!(Memory::from_kib(i64::MAX).bytes(), i64::MAX);
+        assert_eq!(Memory::from_mib(i64::MAX / 1024).bytes(), i64::MAX);
+        assert_eq!(Memory::from_gib(-(1 << 40)).bytes(), i64::MIN);
+
+        let max = Cpu::from_nanocores(i64::MAX);
+        assert_eq!(max + Cpu::from_nanocores(1), max);
+        assert_eq!(Cpu::from_nanocores(-2) - max, Cpu::from_nanocores(i64::MIN));
+        let mut memory = Memory::from_bytes(i64::MAX);
+        memory += Memory::from_bytes(1);
+        assert_eq!(memory.bytes(), i64::MAX);
+        memory -= Memory::from_bytes(1);
+        assert_eq!(memory.bytes(), i64::MAX - 1);
+        assert_eq!([max, max].iter().sum::<Cpu>(), max);
+    }
+}